@@ -0,0 +1,320 @@
+//! Badges rendered from the package metadata.
+
+use cargo_metadata::Package;
+use serde::{Deserialize, Serialize};
+
+use crate::filters::url_encode as encode;
+
+/// Configuration of which badges to render.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Badges {
+    /// The service used to render the badge images.
+    pub provider: Provider,
+
+    /// The badges to render, in order.
+    pub include: Vec<Kind>,
+
+    /// The GitHub Actions workflow file for the CI badge.
+    pub workflow: String,
+
+    /// Badges with custom image and link URLs, rendered after the others.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub custom: Vec<Custom>,
+}
+
+impl Default for Badges {
+    fn default() -> Self {
+        Self {
+            provider: Provider::default(),
+            include: vec![Kind::CratesIo, Kind::DocsRs, Kind::Ci],
+            workflow: "build.yaml".into(),
+            custom: Vec::new(),
+        }
+    }
+}
+
+/// The service used to render the badge images.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Provider {
+    /// <https://shields.io>
+    ShieldsIo,
+    /// <https://badgers.space>
+    #[default]
+    BadgersSpace,
+    /// A URL pattern for static badges containing `{label}`, `{message}` and
+    /// `{color}` placeholders.
+    Custom(String),
+}
+
+/// A badge derived from the package metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    /// The latest version on crates.io.
+    CratesIo,
+    /// The docs.rs documentation.
+    DocsRs,
+    /// The package license.
+    License,
+    /// The minimum supported Rust version from `rust-version`.
+    Msrv,
+    /// The total downloads on crates.io.
+    Downloads,
+    /// The status of the GitHub Actions workflow.
+    Ci,
+}
+
+/// A badge with custom image and link URLs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Custom {
+    pub alt: String,
+    pub image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+/// Renders the badges as Markdown, one per line.
+///
+/// Badges that need metadata the package doesn't have are skipped, e.g. the
+/// MSRV badge without a `rust-version`. So are the downloads and CI badges
+/// with a custom provider, since it only supports static badges.
+pub fn render(badges: &Badges, pkg: &Package) -> String {
+    let name = &pkg.name;
+    let repository = pkg
+        .repository
+        .as_deref()
+        .map(|r| r.trim_end_matches('/').trim_end_matches(".git"));
+    let github = repository.and_then(|r| r.strip_prefix("https://github.com/"));
+
+    let mut lines = Vec::new();
+    for kind in &badges.include {
+        let (alt, image, link) = match kind {
+            Kind::CratesIo => (
+                "Crates.io Version",
+                badges.provider.image(
+                    &format!("https://img.shields.io/crates/v/{name}"),
+                    &format!("https://badgers.space/crates/version/{name}"),
+                    ("crates.io", &pkg.version.to_string(), "orange"),
+                ),
+                Some(format!("https://crates.io/crates/{name}")),
+            ),
+            Kind::DocsRs => (
+                "Docs.rs Latest",
+                badges.provider.image(
+                    &format!("https://img.shields.io/docsrs/{name}"),
+                    "https://badgers.space/badge/docs.rs/latest/blue",
+                    ("docs.rs", "latest", "blue"),
+                ),
+                Some(format!("https://docs.rs/{name}")),
+            ),
+            Kind::License => {
+                let Some(license) = &pkg.license else {
+                    continue;
+                };
+                (
+                    "License",
+                    badges.provider.badge("license", license, "blue"),
+                    None,
+                )
+            }
+            Kind::Msrv => {
+                let Some(version) = &pkg.rust_version else {
+                    continue;
+                };
+                (
+                    "MSRV",
+                    badges
+                        .provider
+                        .badge("rustc", &format!("{version}+"), "lightgray"),
+                    None,
+                )
+            }
+            Kind::Downloads => {
+                let Some(image) = badges.provider.dynamic(
+                    &format!("https://img.shields.io/crates/d/{name}"),
+                    &format!("https://badgers.space/crates/downloads/{name}"),
+                ) else {
+                    continue;
+                };
+                (
+                    "Crates.io Downloads",
+                    image,
+                    Some(format!("https://crates.io/crates/{name}")),
+                )
+            }
+            Kind::Ci => {
+                let Some(repo) = github else {
+                    continue;
+                };
+                let workflow = &badges.workflow;
+                let Some(image) = badges.provider.dynamic(
+                    &format!(
+                        "https://img.shields.io/github/actions/workflow/status/{repo}/{workflow}?label=build"
+                    ),
+                    // badgers.space checks badges can't select a workflow so
+                    // use the badge that GitHub provides for it
+                    &format!("https://github.com/{repo}/actions/workflows/{workflow}/badge.svg"),
+                ) else {
+                    continue;
+                };
+                (
+                    "Build Status",
+                    image,
+                    Some(format!(
+                        "https://github.com/{repo}/actions/workflows/{workflow}"
+                    )),
+                )
+            }
+        };
+        lines.push(markdown(alt, &image, link.as_deref()));
+    }
+    for custom in &badges.custom {
+        lines.push(markdown(&custom.alt, &custom.image, custom.link.as_deref()));
+    }
+    lines.join("\n")
+}
+
+impl Provider {
+    /// Returns the image URL for a badge.
+    ///
+    /// Custom providers only support static badges so they are given a label,
+    /// message and color instead.
+    fn image(&self, shields: &str, badgers: &str, fallback: (&str, &str, &str)) -> String {
+        match self {
+            Self::ShieldsIo => shields.to_owned(),
+            Self::BadgersSpace => badgers.to_owned(),
+            Self::Custom(_) => self.badge(fallback.0, fallback.1, fallback.2),
+        }
+    }
+
+    /// Returns the image URL for a badge whose message comes from the
+    /// provider, like the download count or the build status.
+    ///
+    /// Custom providers only support static badges so there is no image.
+    fn dynamic(&self, shields: &str, badgers: &str) -> Option<String> {
+        match self {
+            Self::ShieldsIo => Some(shields.to_owned()),
+            Self::BadgersSpace => Some(badgers.to_owned()),
+            Self::Custom(_) => None,
+        }
+    }
+
+    /// Returns the image URL for a static badge.
+    fn badge(&self, label: &str, message: &str, color: &str) -> String {
+        match self {
+            Self::ShieldsIo => {
+                // Shields uses `-` as a separator so literal dashes are doubled
+                let escape = |s: &str| encode(&s.replace('-', "--").replace('_', "__"));
+                format!(
+                    "https://img.shields.io/badge/{}-{}-{}",
+                    escape(label),
+                    escape(message),
+                    color
+                )
+            }
+            Self::BadgersSpace => format!(
+                "https://badgers.space/badge/{}/{}/{}",
+                encode(label),
+                encode(message),
+                color
+            ),
+            Self::Custom(pattern) => pattern
+                .replace("{label}", &encode(label))
+                .replace("{message}", &encode(message))
+                .replace("{color}", color),
+        }
+    }
+}
+
+fn markdown(alt: &str, image: &str, link: Option<&str>) -> String {
+    match link {
+        Some(link) => format!("[![{alt}]({image})]({link})"),
+        None => format!("![{alt}]({image})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_badges() {
+        assert_eq!(
+            Provider::ShieldsIo.badge("license", "MIT OR Apache-2.0", "blue"),
+            "https://img.shields.io/badge/license-MIT%20OR%20Apache--2.0-blue"
+        );
+        assert_eq!(
+            Provider::BadgersSpace.badge("rustc", "1.70.0+", "lightgray"),
+            "https://badgers.space/badge/rustc/1.70.0%2B/lightgray"
+        );
+        assert_eq!(
+            Provider::Custom("https://example.com/{label}/{message}?color={color}".into())
+                .badge("docs.rs", "latest", "blue"),
+            "https://example.com/docs.rs/latest?color=blue"
+        );
+    }
+
+    #[test]
+    fn custom_provider_skips_dynamic_badges() {
+        let metadata = cargo_metadata::MetadataCommand::new().exec().unwrap();
+        let pkg = metadata.root_package().unwrap();
+        let badges = Badges {
+            provider: Provider::Custom("https://example.com/{label}/{message}".into()),
+            include: vec![Kind::Downloads, Kind::Ci, Kind::DocsRs],
+            ..Badges::default()
+        };
+        assert_eq!(
+            render(&badges, pkg),
+            "[![Docs.rs Latest](https://example.com/docs.rs/latest)](https://docs.rs/cargo-onedoc)"
+        );
+    }
+
+    #[test]
+    fn ci_badge() {
+        let metadata = cargo_metadata::MetadataCommand::new().exec().unwrap();
+        let mut pkg = metadata.root_package().unwrap().clone();
+        pkg.repository = Some("https://github.com/a/b.git".into());
+        let mut badges = Badges {
+            include: vec![Kind::Ci],
+            workflow: "ci.yml".into(),
+            ..Badges::default()
+        };
+        assert_eq!(
+            render(&badges, &pkg),
+            "[![Build Status](https://github.com/a/b/actions/workflows/ci.yml/badge.svg)](https://github.com/a/b/actions/workflows/ci.yml)"
+        );
+        badges.provider = Provider::ShieldsIo;
+        assert_eq!(
+            render(&badges, &pkg),
+            "[![Build Status](https://img.shields.io/github/actions/workflow/status/a/b/ci.yml?label=build)](https://github.com/a/b/actions/workflows/ci.yml)"
+        );
+    }
+
+    #[test]
+    fn deserialize() {
+        let badges: Badges = toml::from_str(
+            r#"
+provider = { custom = "https://example.com/{label}/{message}/{color}" }
+include = ["license", "msrv"]
+custom = [{ alt = "Chat", image = "https://example.com/chat.svg" }]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            badges,
+            Badges {
+                provider: Provider::Custom("https://example.com/{label}/{message}/{color}".into()),
+                include: vec![Kind::License, Kind::Msrv],
+                workflow: "build.yaml".into(),
+                custom: vec![Custom {
+                    alt: "Chat".into(),
+                    image: "https://example.com/chat.svg".into(),
+                    link: None,
+                }],
+            }
+        );
+    }
+}
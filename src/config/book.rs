@@ -0,0 +1,145 @@
+use super::*;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+/// A chapter in an mdBook `SUMMARY.md`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Chapter {
+    /// The title of the chapter, i.e. the link text.
+    pub title: String,
+    /// The chapter file, relative to the summary directory.
+    pub path: PathBuf,
+    /// How deeply the chapter is nested, top-level chapters are zero.
+    pub depth: usize,
+}
+
+/// Parses the chapters from an mdBook `SUMMARY.md`.
+///
+/// Part titles, separators and draft chapters (chapters without a file) are
+/// skipped.
+pub fn chapters(text: &str) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    let mut lists = 0;
+    let mut link: Option<(String, String)> = None;
+    for event in Parser::new(text) {
+        match event {
+            Event::Start(Tag::List(_)) => lists += 1,
+            Event::End(Tag::List(_)) => lists -= 1,
+            Event::Start(Tag::Link(_, dest, _)) => link = Some((dest.to_string(), String::new())),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, title)) = link.as_mut() {
+                    title.push_str(&text);
+                }
+            }
+            Event::End(Tag::Link(..)) => {
+                let (dest, title) = link.take().unwrap();
+                if !dest.is_empty() {
+                    chapters.push(Chapter {
+                        title,
+                        path: dest.into(),
+                        depth: lists.max(1) - 1,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    chapters
+}
+
+/// Selects the named chapters and their sub-chapters.
+///
+/// The depths of the selected chapters are adjusted so that the shallowest
+/// selected chapter is at depth zero.
+pub fn select(chapters: Vec<Chapter>, names: &[String]) -> Result<Vec<Chapter>> {
+    if let Some(name) = names
+        .iter()
+        .find(|n| !chapters.iter().any(|c| &c.title == *n))
+    {
+        bail!("chapter `{}` not found", name);
+    }
+
+    let mut selected = Vec::new();
+    let mut parent: Option<usize> = None;
+    for mut chapter in chapters {
+        if parent.is_some_and(|depth| chapter.depth <= depth) {
+            parent = None;
+        }
+        if parent.is_none() && names.contains(&chapter.title) {
+            parent = Some(chapter.depth);
+        }
+        if let Some(depth) = parent {
+            chapter.depth -= depth;
+            selected.push(chapter);
+        }
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUMMARY: &str = "\
+# Summary
+
+[Introduction](README.md)
+
+# Guide
+
+- [Installation](guide/installation.md)
+- [Usage](guide/usage.md)
+  - [Configuration](guide/config.md)
+    - [Templates](guide/templates.md)
+  - [Draft]()
+- [FAQ](faq.md)
+
+---
+
+[Contributors](misc/contributors.md)
+";
+
+    fn chapter(title: &str, path: &str, depth: usize) -> Chapter {
+        Chapter {
+            title: title.into(),
+            path: path.into(),
+            depth,
+        }
+    }
+
+    #[test]
+    fn parse_chapters() {
+        assert_eq!(
+            chapters(SUMMARY),
+            vec![
+                chapter("Introduction", "README.md", 0),
+                chapter("Installation", "guide/installation.md", 0),
+                chapter("Usage", "guide/usage.md", 0),
+                chapter("Configuration", "guide/config.md", 1),
+                chapter("Templates", "guide/templates.md", 2),
+                chapter("FAQ", "faq.md", 0),
+                chapter("Contributors", "misc/contributors.md", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn select_chapters() {
+        let names = vec!["Configuration".to_owned(), "FAQ".to_owned()];
+        assert_eq!(
+            select(chapters(SUMMARY), &names).unwrap(),
+            vec![
+                chapter("Configuration", "guide/config.md", 0),
+                chapter("Templates", "guide/templates.md", 1),
+                chapter("FAQ", "faq.md", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn select_unknown_chapter() {
+        let names = vec!["Nope".to_owned()];
+        let err = select(chapters(SUMMARY), &names).unwrap_err();
+        assert_eq!(err.to_string(), "chapter `Nope` not found");
+    }
+}
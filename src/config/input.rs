@@ -2,25 +2,314 @@ use super::*;
 
 use std::fmt;
 
+use pulldown_cmark::{Event, Parser, Tag};
 use serde::de;
 use serde::de::{Deserialize, Deserializer};
 
+/// An input to a doc.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Input {
+    /// A file path.
+    Path(PathBuf),
+    /// A glob pattern, e.g. `docs/src/*.md`.
+    Glob(PathBuf),
+    /// A file path with an explicit kind.
+    File(FileInput),
+    /// A directory of Markdown files.
+    Dir(Dir),
+    /// The chapters of an mdBook.
+    Book(Book),
+}
+
+/// A file path with an explicit kind.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileInput {
+    /// The file path.
+    pub path: PathBuf,
+
+    /// How to process the file.
+    pub kind: Kind,
+}
+
+/// How to process an input file.
+///
+/// Defaults to rustdoc for `.rs` files and Markdown for `.md` files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    /// Crate docs, either the `//!` comments of a Rust file or a Markdown file
+    /// included using `#![doc = include_str!(..)]`.
+    Rustdoc,
+    /// A Markdown file.
+    Markdown,
+}
+
+/// A directory of Markdown files.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dir {
+    /// The directory to read Markdown files from.
+    pub dir: PathBuf,
+
+    /// A Markdown file whose links determine which files to read and in
+    /// which order.
+    ///
+    /// Relative to the directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<PathBuf>,
+}
+
+/// The chapters of an mdBook.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Book {
+    /// The mdBook `SUMMARY.md` file.
+    pub summary: PathBuf,
+
+    /// The titles of the chapters to include, along with their sub-chapters.
+    ///
+    /// Defaults to all chapters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chapters: Option<Vec<String>>,
+}
+
+/// An input file, expanded from an [`Input`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct File {
+    /// The file path.
+    pub path: PathBuf,
+
+    /// How many levels to increase the headings by, in addition to the usual
+    /// one level.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub depth: usize,
+
+    /// How to process the file, if not determined by its extension.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl From<PathBuf> for File {
+    fn from(path: PathBuf) -> Self {
+        Self {
+            path,
+            depth: 0,
+            kind: None,
+        }
+    }
+}
+
+impl From<PathBuf> for Input {
+    /// Returns a glob pattern if the path contains glob characters.
+    fn from(path: PathBuf) -> Self {
+        if is_glob(&path) {
+            Self::Glob(path)
+        } else {
+            Self::Path(path)
+        }
+    }
+}
+
+impl From<&str> for Input {
+    fn from(path: &str) -> Self {
+        PathBuf::from(path).into()
+    }
+}
+
+impl Input {
+    /// Make the paths relative to the given directory.
+    pub fn normalize(&mut self, dir: &Path) {
+        match self {
+            Self::Path(p) => *p = dir.join(&p),
+            // The directory may contain glob characters itself
+            Self::Glob(p) => *p = PathBuf::from(glob::Pattern::escape(dir.as_str())).join(&p),
+            Self::File(f) => f.path = dir.join(&f.path),
+            Self::Dir(d) => d.dir = dir.join(&d.dir),
+            Self::Book(b) => b.summary = dir.join(&b.summary),
+        }
+    }
+
+    /// Expand the input into a list of files.
+    ///
+    /// Glob patterns and directories are expanded in sorted order so that the
+    /// result does not depend on the file system.
+    pub fn expand(&self) -> Result<Vec<File>> {
+        let files = match self {
+            Self::Glob(p) => {
+                let files = glob(p.as_str())?;
+                if files.is_empty() {
+                    bail!("pattern `{}` did not match any files", p);
+                }
+                files
+            }
+            Self::Path(p) => vec![p.clone()],
+            Self::File(FileInput { path, kind }) => {
+                return Ok(vec![File {
+                    path: path.clone(),
+                    depth: 0,
+                    kind: Some(*kind),
+                }])
+            }
+            Self::Dir(Dir { dir, order: None }) => {
+                // The directory may contain glob characters itself
+                let pattern = PathBuf::from(glob::Pattern::escape(dir.as_str())).join("**/*.md");
+                let files = glob(pattern.as_str())?;
+                if files.is_empty() {
+                    bail!("directory `{}` did not match any files", dir);
+                }
+                files
+            }
+            Self::Dir(Dir {
+                dir,
+                order: Some(order),
+            }) => {
+                let path = dir.join(order);
+                let text = fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read from `{}`", path))?;
+                let files = ordered(dir, &text);
+                if let Some(missing) = files.iter().find(|f| !f.is_file()) {
+                    bail!("file `{}` linked from `{}` does not exist", missing, path);
+                }
+                files
+            }
+            Self::Book(Book { summary, chapters }) => {
+                let text = fs::read_to_string(summary)
+                    .with_context(|| format!("failed to read from `{}`", summary))?;
+                let mut all = book::chapters(&text);
+                if let Some(names) = chapters {
+                    all = book::select(all, names)
+                        .with_context(|| format!("invalid chapters for `{}`", summary))?;
+                }
+                let dir = summary.parent().unwrap();
+                return Ok(all
+                    .into_iter()
+                    .map(|c| File {
+                        path: dir.join(c.path),
+                        depth: c.depth,
+                        kind: None,
+                    })
+                    .collect());
+            }
+        };
+        Ok(files.into_iter().map(File::from).collect())
+    }
+}
+
+/// Returns whether the path contains any glob special characters.
+fn is_glob(path: &Path) -> bool {
+    path.as_str().contains(['*', '?', '['])
+}
+
+/// Returns the sorted files matching the pattern.
+fn glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let paths =
+        glob::glob(pattern).with_context(|| format!("invalid glob pattern `{}`", pattern))?;
+    let mut files = Vec::new();
+    for path in paths {
+        let path = PathBuf::try_from(path?)?;
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Returns the files linked to in the order text, in order.
+fn ordered(dir: &Path, text: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for event in Parser::new(text) {
+        if let Event::Start(Tag::Link(_, dest, _)) = event {
+            if dest.contains("://") || dest.starts_with('#') || dest.starts_with("mailto:") {
+                continue;
+            }
+            let path = dest.split('#').next().unwrap();
+            if path.is_empty() {
+                continue;
+            }
+            let file = dir.join(path);
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    files
+}
+
+impl<'de> Deserialize<'de> for Input {
+    /// Deserializes a path, glob pattern or table.
+    ///
+    /// The kind of table is picked using the keys present so that errors,
+    /// such as unknown keys, are reported for that kind of input.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(InputVisitor)
+    }
+}
+
+struct InputVisitor;
+
+impl<'de> de::Visitor<'de> for InputVisitor {
+    type Value = Input;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("string or table")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.into())
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let d = de::value::MapAccessDeserializer::new(map);
+        let table: toml::Table = Deserialize::deserialize(d)?;
+        let input = if table.contains_key("path") {
+            toml::Value::Table(table).try_into().map(Input::File)
+        } else if table.contains_key("dir") {
+            toml::Value::Table(table).try_into().map(Input::Dir)
+        } else if table.contains_key("summary") {
+            toml::Value::Table(table).try_into().map(Input::Book)
+        } else {
+            const KEYS: &[&str] = &["path", "dir", "summary"];
+            return Err(match table.keys().next() {
+                Some(key) => de::Error::unknown_field(key, KEYS),
+                None => de::Error::custom(
+                    "expected a table with either a `path`, `dir` or `summary` key",
+                ),
+            });
+        };
+        input.map_err(|err| de::Error::custom(err.message()))
+    }
+}
+
 struct Visitor;
 
 impl<'de> de::Visitor<'de> for Visitor {
-    type Value = Vec<PathBuf>;
+    type Value = Vec<Input>;
 
     fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("string or map")
+        f.write_str("string, sequence or table")
     }
 
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        let d = de::value::StrDeserializer::new(v);
-        let path: PathBuf = Deserialize::deserialize(d)?;
-        Ok(vec![path])
+        Ok(vec![v.into()])
     }
 
     fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
@@ -30,11 +319,178 @@ impl<'de> de::Visitor<'de> for Visitor {
         let d = de::value::SeqAccessDeserializer::new(seq);
         Deserialize::deserialize(d)
     }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        Ok(vec![de::Visitor::visit_map(InputVisitor, map)?])
+    }
 }
 
-pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error>
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Input>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    deserializer.deserialize_str(Visitor)
+    deserializer.deserialize_any(Visitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_glob() {
+        let files = Input::from("src/fix/*.rs").expand().unwrap();
+        assert!(files.contains(&File::from(PathBuf::from("src/fix/alerts.rs"))));
+        assert!(files.windows(2).all(|w| w[0].path < w[1].path));
+    }
+
+    #[test]
+    fn expand_glob_no_matches() {
+        let err = Input::from("src/*.nope").expand().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "pattern `src/*.nope` did not match any files"
+        );
+    }
+
+    #[test]
+    fn glob_characters_in_dir() {
+        let dir = std::env::temp_dir().join(format!("onedoc-[{}]", std::process::id()));
+        let dir = PathBuf::try_from(dir).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), "").unwrap();
+
+        let mut path = Input::from("a.md");
+        path.normalize(&dir);
+        let mut pattern = Input::from("*.md");
+        pattern.normalize(&dir);
+        let mut docs = Input::Dir(Dir {
+            dir: ".".into(),
+            order: None,
+        });
+        docs.normalize(&dir);
+        let expanded = (path.expand(), pattern.expand(), docs.expand());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(path, Input::Path(_)));
+        assert_eq!(expanded.0.unwrap(), vec![File::from(dir.join("a.md"))]);
+        assert_eq!(expanded.1.unwrap(), vec![File::from(dir.join("a.md"))]);
+        assert_eq!(expanded.2.unwrap(), vec![File::from(dir.join("a.md"))]);
+    }
+
+    #[test]
+    fn expand_dir_errors() {
+        let dir = std::env::temp_dir().join(format!("onedoc-dir-{}", std::process::id()));
+        let dir = PathBuf::try_from(dir).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("SUMMARY.md"), "- [A](a.md)\n").unwrap();
+
+        let empty = Input::Dir(Dir {
+            dir: dir.join("empty"),
+            order: None,
+        })
+        .expand();
+        let missing = Input::Dir(Dir {
+            dir: dir.clone(),
+            order: Some("SUMMARY.md".into()),
+        })
+        .expand();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            empty.unwrap_err().to_string(),
+            format!("directory `{}` did not match any files", dir.join("empty"))
+        );
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            format!(
+                "file `{}` linked from `{}` does not exist",
+                dir.join("a.md"),
+                dir.join("SUMMARY.md")
+            )
+        );
+    }
+
+    #[test]
+    fn deserialize_dir() {
+        let doc: Doc = toml::from_str(
+            r#"
+input = ["src/lib.rs", { dir = "docs/src", order = "SUMMARY.md" }]
+output = "README.md"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            doc.inputs,
+            vec![
+                Input::from("src/lib.rs"),
+                Input::Dir(Dir {
+                    dir: "docs/src".into(),
+                    order: Some("SUMMARY.md".into()),
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_file_kind() {
+        let doc: Doc = toml::from_str(
+            r#"
+input = ["docs/*.md", { path = "docs/crate.md", kind = "rustdoc" }]
+output = "README.md"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            doc.inputs,
+            vec![
+                Input::Glob("docs/*.md".into()),
+                Input::File(FileInput {
+                    path: "docs/crate.md".into(),
+                    kind: Kind::Rustdoc,
+                })
+            ]
+        );
+        assert_eq!(doc.inputs[1].expand().unwrap()[0].kind, Some(Kind::Rustdoc));
+    }
+
+    #[test]
+    fn deserialize_book() {
+        let doc: Doc = toml::from_str(
+            r#"
+input = { summary = "book/src/SUMMARY.md", chapters = ["Usage"] }
+output = "README.md"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            doc.inputs,
+            vec![Input::Book(Book {
+                summary: "book/src/SUMMARY.md".into(),
+                chapters: Some(vec!["Usage".into()]),
+            })]
+        );
+    }
+
+    #[test]
+    fn ordered_links() {
+        let text = "\
+# Summary
+
+- [Intro](intro.md)
+- [Usage](usage/README.md#start)
+  - [Again](intro.md)
+- [External](https://example.com)
+- [Anchor](#anchor)
+";
+        assert_eq!(
+            ordered(Path::new("docs"), text),
+            vec![
+                PathBuf::from("docs/intro.md"),
+                PathBuf::from("docs/usage/README.md")
+            ]
+        );
+    }
 }
@@ -1,33 +1,85 @@
+mod book;
 mod input;
 
-use std::collections::HashMap;
+pub use crate::config::input::{File, Input, Kind};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::io;
 
-use anyhow::{anyhow, Context as _, Result};
+use anyhow::{anyhow, bail, Context as _, Result};
 use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
 use cargo_metadata::{Metadata, Package};
-use serde::Deserialize;
+use regex_macro::regex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::badges::Badges;
+use crate::target::Target;
+use crate::templates;
 
 /// Configuration of which files to process.
-#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Another config file to inherit from.
+    ///
+    /// Relative to the directory of the config it is specified in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<PathBuf>,
+
+    /// The default template for docs that don't specify one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<PathBuf>,
+
+    /// A directory of templates that can be included or extended by templates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub templates: Option<PathBuf>,
+
+    /// Which badges to render for the `badges` template value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badges: Option<Badges>,
+
     /// A list of processes that each outputs a single Markdown file
     #[serde(default, rename = "doc")]
     pub docs: Vec<Doc>,
 
+    /// Environment variables to expose to templates as `env`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+
+    /// Whether to expose `[package.metadata]` to templates as `metadata`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<bool>,
+
     /// Global link remapping config
     #[serde(default)]
-    pub links: HashMap<String, String>,
+    pub links: BTreeMap<String, String>,
+
+    /// Global template variables, exposed to templates as `vars`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, toml::Value>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Doc {
-    /// A list of input file paths.
+    /// An optional name used to select this doc from the command line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// A list of inputs, each a file path, a glob pattern or a directory.
     ///
     /// Either absolute paths or relative to the Cargo workspace directory.
     #[serde(rename = "input", deserialize_with = "input::deserialize")]
-    pub inputs: Vec<PathBuf>,
+    pub inputs: Vec<Input>,
+
+    /// The input files, expanded from the inputs when the config is loaded.
+    ///
+    /// Not part of the config file format, see [`Config::expanded`].
+    #[serde(skip)]
+    pub files: Vec<File>,
 
     /// The output file path.
     ///
@@ -35,53 +87,433 @@ pub struct Doc {
     pub output: PathBuf,
 
     /// The template to render the processed Markdown
+    ///
+    /// Either a file path or `builtin:<name>` for a built-in template.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub template: Option<PathBuf>,
+
+    /// An inline template, used instead of `template`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_str: Option<String>,
+
+    /// Which direction to generate documentation in.
+    #[serde(default)]
+    pub direction: Direction,
+
+    /// How to write the output file.
+    #[serde(default)]
+    pub mode: Mode,
+
+    /// The destination that the output will be rendered on.
+    #[serde(default)]
+    pub target: Target,
+
+    /// How to render rustdoc warning blocks and note paragraphs.
+    ///
+    /// Defaults to the target's default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<Alerts>,
+
+    /// Link remapping config for this doc, merged with the global config.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<String, String>,
+
+    /// Template variables for this doc, merged with the global variables.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, toml::Value>,
+}
+
+impl fmt::Display for Doc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", self.output, name),
+            None => write!(f, "{}", self.output),
+        }
+    }
+}
+
+impl Doc {
+    /// Returns how to render alerts for this doc.
+    pub fn alerts(&self) -> Alerts {
+        self.alerts.unwrap_or_else(|| self.target.alerts())
+    }
+}
+
+/// Which direction to generate documentation in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    /// Generate Markdown from Rust doc comments and Markdown files.
+    #[default]
+    ToMarkdown,
+    /// Generate the `//!` crate docs of a Rust file from Markdown files.
+    ToRustdoc,
 }
 
+/// How to write the output file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Replace the whole file with the rendered template.
+    #[default]
+    Overwrite,
+    /// Only replace the marked regions of the existing file.
+    Inject,
+}
+
+/// How to render alerts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Alerts {
+    /// As GitHub alerts, e.g. `> [!WARNING]`.
+    #[default]
+    Github,
+    /// As plain block quotes with a bold label, e.g. `> **Warning:**`.
+    Blockquote,
+}
+
+/// Load the configuration for a package.
+///
+/// Configuration is read from the following places, in order, with later ones
+/// taking precedence:
+///
+/// - `[workspace.metadata.onedoc]` in the workspace `Cargo.toml`.
+/// - `[package.metadata.onedoc]` in the package `Cargo.toml`.
+/// - `onedoc.toml` in the workspace directory.
+///
+/// Paths in the package metadata are relative to the package directory, all
+/// other paths are relative to the workspace directory.
 pub fn load(metadata: &Metadata, pkg: &Package) -> Result<Config> {
     let workspace_dir = &metadata.workspace_root;
+    let package_dir = pkg.manifest_path.parent().unwrap();
     let path = workspace_dir.join("onedoc.toml");
 
-    let mut config = {
-        let ctx = || format!("failed to load config from `{}`", path);
-        load_from_path(&path).with_context(ctx)?
-    };
+    let layers = [
+        (
+            load_from_workspace_manifest(&workspace_dir.join("Cargo.toml"))
+                .context("failed to load config from `[workspace.metadata.onedoc]`")?,
+            workspace_dir.as_path(),
+        ),
+        (
+            load_from_package_manifest(&pkg.manifest_path)
+                .context("failed to load config from `[package.metadata.onedoc]`")?,
+            package_dir,
+        ),
+        (
+            load_from_path(&path)
+                .with_context(|| format!("failed to load config from `{}`", path))?,
+            workspace_dir.as_path(),
+        ),
+    ];
+
+    let mut config = Config::default();
+    for (layer, dir) in layers {
+        let layer = resolve(layer, dir, &mut Vec::new())?;
+        config.merge(layer);
+    }
+
+    validate(&config).context("invalid config")?;
 
     // Make sure to specify at least one doc to process
     if config.docs.is_empty() {
         config.docs = vec![default_doc(pkg)?]
     }
 
-    // Normalize all the paths
+    // Apply the default template
     for doc in &mut config.docs {
-        for input in &mut doc.inputs {
-            *input = workspace_dir.join(&input);
+        if doc.template.is_none() && doc.template_str.is_none() {
+            doc.template = config.template.clone();
         }
-        doc.output = workspace_dir.join(&doc.output);
-        if let Some(p) = doc.template.as_mut() {
-            *p = workspace_dir.join(&p);
+    }
+
+    // Expand glob patterns and directories
+    for doc in &mut config.docs {
+        doc.files.clear();
+        for input in &doc.inputs {
+            let files = input
+                .expand()
+                .with_context(|| format!("failed to expand inputs for {}", doc))?;
+            doc.files.extend(files);
         }
     }
 
+    check_paths(&config).context("invalid config")?;
+
     Ok(config)
 }
 
+/// Normalize the paths in the config and resolve the config it extends.
+fn resolve(mut config: Config, dir: &Path, seen: &mut Vec<PathBuf>) -> Result<Config> {
+    let extends = config.extends.take();
+    config.normalize(dir);
+
+    let Some(extends) = extends else {
+        return Ok(config);
+    };
+
+    let path = dir.join(extends);
+    let ctx = || format!("failed to load config from `{}`", path);
+    let canonical = path.canonicalize_utf8().with_context(ctx)?;
+    if seen.contains(&canonical) {
+        bail!("config `{}` extends itself", path);
+    }
+    seen.push(canonical);
+
+    let contents = fs::read_to_string(&path).with_context(ctx)?;
+    let base: Config = parse(&contents)
+        .context("failed to deserialize config")
+        .with_context(ctx)?;
+    let mut base = resolve(base, path.parent().unwrap(), seen)?;
+    base.merge(config);
+    Ok(base)
+}
+
+impl Config {
+    /// Returns the expanded input files of each doc as TOML comments.
+    ///
+    /// The files aren't part of the config file format, so printing them as
+    /// comments keeps the printed config loadable.
+    pub fn expanded(&self) -> String {
+        let mut out = String::new();
+        for doc in &self.docs {
+            out.push_str(&format!("\n# Input files for {}:\n", doc));
+            for file in &doc.files {
+                out.push_str(&format!("#   {}\n", file.path));
+            }
+        }
+        out
+    }
+
+    /// Returns the link remapping config for the given doc.
+    pub fn links(&self, doc: &Doc) -> BTreeMap<String, String> {
+        let mut links = self.links.clone();
+        links.extend(doc.links.clone());
+        links
+    }
+
+    /// Returns the template variables for the given doc.
+    pub fn vars(&self, doc: &Doc) -> BTreeMap<String, toml::Value> {
+        let mut vars = self.vars.clone();
+        vars.extend(doc.vars.clone());
+        vars
+    }
+
+    /// Returns the exposed environment variables, unset ones are `None`.
+    pub fn env(&self) -> BTreeMap<String, Option<String>> {
+        self.env
+            .iter()
+            .map(|name| (name.clone(), std::env::var(name).ok()))
+            .collect()
+    }
+
+    /// Make all the paths relative to the given directory.
+    fn normalize(&mut self, dir: &Path) {
+        if let Some(p) = self.template.as_mut() {
+            *p = join_template(dir, p);
+        }
+        if let Some(p) = self.templates.as_mut() {
+            *p = dir.join(&p);
+        }
+        for doc in &mut self.docs {
+            for input in &mut doc.inputs {
+                input.normalize(dir);
+            }
+            doc.output = dir.join(&doc.output);
+            if let Some(p) = doc.template.as_mut() {
+                *p = join_template(dir, p);
+            }
+        }
+    }
+
+    /// Merge another config into this one, the other config takes precedence.
+    ///
+    /// The docs are replaced if the other config specifies any, the template,
+    /// templates directory, badges and metadata flag are replaced if the other
+    /// config specifies them and the environment variables, links and vars are
+    /// merged.
+    fn merge(&mut self, other: Config) {
+        if other.template.is_some() {
+            self.template = other.template;
+        }
+        if other.templates.is_some() {
+            self.templates = other.templates;
+        }
+        if other.badges.is_some() {
+            self.badges = other.badges;
+        }
+        if !other.docs.is_empty() {
+            self.docs = other.docs;
+        }
+        for name in other.env {
+            if !self.env.contains(&name) {
+                self.env.push(name);
+            }
+        }
+        if other.metadata.is_some() {
+            self.metadata = other.metadata;
+        }
+        self.links.extend(other.links);
+        self.vars.extend(other.vars);
+    }
+}
+
+/// Makes a template path relative to the given directory, leaving built-in
+/// template names as they are.
+fn join_template(dir: &Path, path: &Path) -> PathBuf {
+    match templates::builtin_name(path) {
+        Some(_) => path.to_owned(),
+        None => dir.join(path),
+    }
+}
+
+fn validate(config: &Config) -> Result<()> {
+    // Make sure doc names are unique
+    let mut names = HashSet::new();
+    for name in config.docs.iter().filter_map(|d| d.name.as_ref()) {
+        if !names.insert(name) {
+            bail!("duplicate doc name `{}`", name);
+        }
+    }
+    for doc in &config.docs {
+        if doc.template.is_some() && doc.template_str.is_some() {
+            bail!("{} can't specify both `template` and `template_str`", doc);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that all the paths in the config exist.
+fn check_paths(config: &Config) -> Result<()> {
+    if let Some(dir) = &config.templates {
+        if !dir.is_dir() {
+            bail!("templates directory `{}` does not exist", dir);
+        }
+    }
+    for doc in &config.docs {
+        for file in &doc.files {
+            if !file.path.is_file() {
+                bail!("input file `{}` for {} does not exist", file.path, doc);
+            }
+        }
+        if let Some(template) = &doc.template {
+            if let Some(name) = templates::builtin_name(template) {
+                templates::builtin(name)
+                    .with_context(|| format!("invalid template for {}", doc))?;
+            } else if !template.is_file() {
+                bail!("template file `{}` for {} does not exist", template, doc);
+            }
+        }
+        // Injecting and generating rustdoc rewrite parts of an existing file
+        if doc.mode == Mode::Inject || doc.direction == Direction::ToRustdoc {
+            if !doc.output.is_file() {
+                bail!("output file `{}` for {} does not exist", doc.output, doc);
+            }
+        } else if let Some(dir) = doc.output.parent().filter(|d| !d.as_str().is_empty()) {
+            if !dir.is_dir() {
+                bail!("output directory `{}` for {} does not exist", dir, doc);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses a config, suggesting a similar name for unknown fields and variants.
+fn parse<T: DeserializeOwned>(contents: &str) -> Result<T> {
+    toml::from_str(contents).map_err(|err| match suggest(err.message()) {
+        Some(name) => anyhow!(
+            "{}\nhelp: did you mean `{}`?",
+            err.to_string().trim_end(),
+            name
+        ),
+        None => anyhow!(err),
+    })
+}
+
+/// Returns the closest expected name for an unknown field or variant error.
+fn suggest(message: &str) -> Option<&str> {
+    let caps = regex!(r"^unknown (?:field|variant) `(.*?)`, expected (.*)$").captures(message)?;
+    let unknown = caps.get(1)?.as_str();
+    regex!(r"`(.*?)`")
+        .captures_iter(caps.get(2)?.as_str())
+        .filter_map(|c| c.get(1))
+        // Transposed letters in short names score low, so allow a single edit
+        .filter(|m| {
+            strsim::jaro_winkler(unknown, m.as_str()) > 0.8
+                || strsim::osa_distance(unknown, m.as_str()) <= 1
+        })
+        .map(|m| (strsim::jaro_winkler(unknown, m.as_str()), m.as_str()))
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, name)| name)
+}
+
 fn load_from_path(path: &Path) -> Result<Config> {
     let config = match fs::read_to_string(path) {
-        Ok(contents) => toml::from_str(&contents).context("failed to deserialize config")?,
+        Ok(contents) => parse(&contents).context("failed to deserialize config")?,
         Err(err) if err.kind() == io::ErrorKind::NotFound => Config::default(),
         Err(err) => return Err(err).context("failed to read config file")?,
     };
     Ok(config)
 }
 
+/// The parts of a Cargo manifest that can contain config.
+///
+/// The manifest is parsed again instead of using the metadata from `cargo
+/// metadata` so that errors point at the location in the manifest.
+#[derive(Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    metadata: ManifestMetadata,
+}
+
+#[derive(Default, Deserialize)]
+struct ManifestMetadata {
+    onedoc: Option<Config>,
+}
+
+#[derive(Deserialize)]
+struct PackageManifest {
+    package: Option<Manifest>,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceManifest {
+    workspace: Option<Manifest>,
+}
+
+fn load_from_package_manifest(path: &Path) -> Result<Config> {
+    let contents = fs::read_to_string(path).context("failed to read manifest")?;
+    let manifest: PackageManifest = parse(&contents).context("failed to deserialize config")?;
+    Ok(manifest
+        .package
+        .and_then(|p| p.metadata.onedoc)
+        .unwrap_or_default())
+}
+
+fn load_from_workspace_manifest(path: &Path) -> Result<Config> {
+    let contents = fs::read_to_string(path).context("failed to read manifest")?;
+    let manifest: WorkspaceManifest = parse(&contents).context("failed to deserialize config")?;
+    Ok(manifest
+        .workspace
+        .and_then(|w| w.metadata.onedoc)
+        .unwrap_or_default())
+}
+
 fn default_doc(pkg: &Package) -> Result<Doc> {
     let input = default_input_path(pkg)?;
     let output = default_output_path(pkg);
     let doc = Doc {
-        inputs: vec![input],
+        name: None,
+        inputs: vec![Input::Path(input)],
+        files: Vec::new(),
         output,
         template: None,
+        template_str: None,
+        direction: Direction::default(),
+        mode: Mode::default(),
+        target: Target::default(),
+        alerts: None,
+        links: BTreeMap::new(),
+        vars: BTreeMap::new(),
     };
     Ok(doc)
 }
@@ -127,12 +559,28 @@ template = "docs/README_TEMPLATE.md"
         assert_eq!(
             config,
             Config {
+                extends: None,
+                template: None,
+                templates: None,
+                badges: None,
                 docs: vec![Doc {
+                    name: None,
                     inputs: vec!["src/lib.rs".into()],
+                    files: Vec::new(),
                     output: "README.md".into(),
                     template: Some("docs/README_TEMPLATE.md".into()),
+                    template_str: None,
+                    direction: Direction::ToMarkdown,
+                    mode: Mode::Overwrite,
+                    target: Target::Github,
+                    alerts: None,
+                    links: BTreeMap::new(),
+                    vars: BTreeMap::new(),
                 },],
-                links: HashMap::new(),
+                env: Vec::new(),
+                metadata: None,
+                links: BTreeMap::new(),
+                vars: BTreeMap::new(),
             }
         );
     }
@@ -152,13 +600,225 @@ template = "docs/README_TEMPLATE.md"
         assert_eq!(
             config,
             Config {
+                extends: None,
+                template: None,
+                templates: None,
+                badges: None,
                 docs: vec![Doc {
+                    name: None,
                     inputs: vec!["src/lib.rs".into(), "src/other.rs".into()],
+                    files: Vec::new(),
                     output: "README.md".into(),
                     template: Some("docs/README_TEMPLATE.md".into()),
+                    template_str: None,
+                    direction: Direction::ToMarkdown,
+                    mode: Mode::Overwrite,
+                    target: Target::Github,
+                    alerts: None,
+                    links: BTreeMap::new(),
+                    vars: BTreeMap::new(),
                 }],
-                links: HashMap::new(),
+                env: Vec::new(),
+                metadata: None,
+                links: BTreeMap::new(),
+                vars: BTreeMap::new(),
             }
         );
     }
+
+    #[test]
+    fn duplicate_doc_names() {
+        let config: Config = toml::from_str(
+            r#"
+[[ doc ]]
+name = "readme"
+input = "src/lib.rs"
+output = "README.md"
+
+[[ doc ]]
+name = "readme"
+input = "src/lib.rs"
+output = "OTHER.md"
+"#,
+        )
+        .unwrap();
+
+        let err = validate(&config).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate doc name `readme`");
+    }
+
+    #[test]
+    fn builtin_and_inline_templates() {
+        let mut config: Config = toml::from_str(
+            r#"
+template = "builtin:minimal"
+
+[[ doc ]]
+input = "src/lib.rs"
+output = "README.md"
+template = "builtin:library"
+template_str = "{{ summary }}"
+"#,
+        )
+        .unwrap();
+
+        let err = validate(&config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "README.md can't specify both `template` and `template_str`"
+        );
+
+        config.normalize(Path::new("/pkg"));
+        assert_eq!(config.template, Some("builtin:minimal".into()));
+        assert_eq!(config.docs[0].template, Some("builtin:library".into()));
+    }
+
+    #[test]
+    fn metadata_layers() {
+        let workspace: WorkspaceManifest = parse(
+            r#"
+[workspace]
+members = ["pkg"]
+
+[workspace.metadata.onedoc]
+doc = [{ input = "src/lib.rs", output = "README.md" }]
+links = { "String" = "https://a", "Vec" = "https://b" }
+"#,
+        )
+        .unwrap();
+        let package: PackageManifest = parse(
+            r#"
+[package]
+name = "pkg"
+
+[package.metadata.onedoc]
+doc = [{ input = ["src/lib.rs", "docs/extra.md"], output = "README.md" }]
+links = { "Vec" = "https://c" }
+"#,
+        )
+        .unwrap();
+
+        let mut config = workspace.workspace.unwrap().metadata.onedoc.unwrap();
+        config.normalize("/ws".into());
+        let mut layer = package.package.unwrap().metadata.onedoc.unwrap();
+        layer.normalize("/ws/pkg".into());
+        config.merge(layer);
+
+        assert_eq!(config.docs.len(), 1);
+        assert_eq!(
+            config.docs[0].inputs,
+            vec![
+                Input::from("/ws/pkg/src/lib.rs"),
+                Input::from("/ws/pkg/docs/extra.md")
+            ]
+        );
+        assert_eq!(config.docs[0].output, "/ws/pkg/README.md");
+        assert_eq!(config.links["String"], "https://a");
+        assert_eq!(config.links["Vec"], "https://c");
+    }
+
+    #[test]
+    fn unknown_field() {
+        let err = parse::<Config>(
+            r#"
+[[ doc ]]
+inputs = "src/lib.rs"
+output = "README.md"
+"#,
+        )
+        .unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("line 3, column 1"), "{msg}");
+        assert!(msg.ends_with("help: did you mean `input`?"), "{msg}");
+    }
+
+    #[test]
+    fn unknown_input_keys() {
+        for (input, name) in [
+            (r#"{ dri = "docs" }"#, "dir"),
+            (r#"["src/lib.rs", { dri = "docs" }]"#, "dir"),
+            (r#"{ dir = "docs", ordr = "SUMMARY.md" }"#, "order"),
+            (r#"[{ path = "docs/crate.md", knd = "rustdoc" }]"#, "kind"),
+        ] {
+            let err = parse::<Config>(&format!(
+                "[[ doc ]]\ninput = {input}\noutput = \"README.md\"\n"
+            ))
+            .unwrap_err();
+            let msg = err.to_string();
+            assert!(
+                msg.ends_with(&format!("help: did you mean `{name}`?")),
+                "{msg}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_variant() {
+        let err = parse::<Config>(
+            r#"
+[[ doc ]]
+input = "src/lib.rs"
+output = "README.md"
+target = "github-flavored"
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().ends_with("help: did you mean `github`?"));
+    }
+
+    #[test]
+    fn suggest_nothing_similar() {
+        assert_eq!(
+            suggest("unknown field `zzz`, expected `input` or `output`"),
+            None
+        );
+    }
+
+    #[test]
+    fn doc_links_override() {
+        let config: Config = toml::from_str(
+            r#"
+template = "TEMPLATE.md"
+
+[[ doc ]]
+input = "src/lib.rs"
+output = "README.md"
+links = { "Vec" = "https://c" }
+
+[links]
+"String" = "https://a"
+"Vec" = "https://b"
+"#,
+        )
+        .unwrap();
+
+        let links = config.links(&config.docs[0]);
+        assert_eq!(links["String"], "https://a");
+        assert_eq!(links["Vec"], "https://c");
+        assert_eq!(config.template, Some("TEMPLATE.md".into()));
+    }
+
+    #[test]
+    fn doc_vars_override() {
+        let config: Config = toml::from_str(
+            r#"
+[[ doc ]]
+input = "src/lib.rs"
+output = "README.md"
+vars = { discord = "https://discord.gg/b" }
+
+[vars]
+discord = "https://discord.gg/a"
+msrv_policy = "https://example.com/msrv"
+"#,
+        )
+        .unwrap();
+
+        let vars = config.vars(&config.docs[0]);
+        assert_eq!(vars["discord"].as_str(), Some("https://discord.gg/b"));
+        assert_eq!(
+            vars["msrv_policy"].as_str(),
+            Some("https://example.com/msrv")
+        );
+    }
 }
@@ -0,0 +1,169 @@
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+
+use camino::Utf8Path as Path;
+use similar::{ChangeTag, TextDiff};
+
+/// Options for how to show the difference when a file is out of date.
+#[derive(Debug, Clone, clap::Args)]
+pub struct Options {
+    /// The number of lines of context to show around changes.
+    #[clap(long, value_name = "LINES", default_value_t = 3)]
+    pub diff_context: usize,
+
+    /// The format to show the difference in.
+    #[clap(long, value_name = "FORMAT", value_enum, default_value_t = Format::Unified)]
+    pub diff_format: Format,
+
+    /// When to use colors in the output.
+    #[clap(long, value_name = "WHEN", value_enum, default_value_t = Color::Auto)]
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// A unified diff.
+    Unified,
+    /// GitHub Actions workflow commands that annotate the changed lines.
+    Github,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Render the difference between the current and the rendered contents.
+///
+/// The path is shown relative to the current directory if possible.
+pub fn render(opts: &Options, path: &Path, current: &str, rendered: &str) -> String {
+    let path = std::env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path);
+    let diff = TextDiff::from_lines(current, rendered);
+    match opts.diff_format {
+        Format::Unified => {
+            let color = match opts.color {
+                Color::Auto => std::io::stdout().is_terminal(),
+                Color::Always => true,
+                Color::Never => false,
+            };
+            unified(&diff, opts.diff_context, path, color)
+        }
+        Format::Github => github(&diff, opts.diff_context, path),
+    }
+}
+
+fn unified<'a>(
+    diff: &'a TextDiff<'a, 'a, 'a, str>,
+    context: usize,
+    path: &Path,
+    color: bool,
+) -> String {
+    let text = diff
+        .unified_diff()
+        .context_radius(context)
+        .header(&format!("a/{path}"), &format!("b/{path}"))
+        .to_string();
+    if !color {
+        return text;
+    }
+    let mut buf = String::new();
+    for line in text.lines() {
+        let code = match line.chars().next() {
+            Some('+') if !line.starts_with("+++") => "32",
+            Some('-') if !line.starts_with("---") => "31",
+            Some('@') => "36",
+            _ => "",
+        };
+        if code.is_empty() {
+            writeln!(buf, "{line}").unwrap();
+        } else {
+            writeln!(buf, "\x1b[{code}m{line}\x1b[0m").unwrap();
+        }
+    }
+    buf
+}
+
+fn github<'a>(diff: &'a TextDiff<'a, 'a, 'a, str>, context: usize, path: &Path) -> String {
+    let mut buf = String::new();
+    for group in diff.grouped_ops(context) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let start = first.old_range().start + 1;
+        let end = last.old_range().end.max(start);
+
+        let mut message = String::new();
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let sign = match change.tag() {
+                    ChangeTag::Delete => '-',
+                    ChangeTag::Insert => '+',
+                    ChangeTag::Equal => ' ',
+                };
+                message.push(sign);
+                message.push_str(change.as_str().unwrap_or_default());
+                if change.missing_newline() {
+                    message.push('\n');
+                }
+            }
+        }
+        writeln!(
+            buf,
+            "::error file={},line={start},endLine={end},title={}::{}",
+            escape_property(path.as_str()),
+            escape_property(&format!("{path} is out of date")),
+            escape(&message)
+        )
+        .unwrap();
+    }
+    buf
+}
+
+/// Escape data for a GitHub Actions workflow command.
+fn escape(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value for a GitHub Actions workflow command.
+fn escape_property(s: &str) -> String {
+    escape(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_diff() {
+        let diff = TextDiff::from_lines("a\nb\nc\n", "a\nB\nc\n");
+        assert_eq!(
+            unified(&diff, 1, "README.md".into(), false),
+            "--- a/README.md\n+++ b/README.md\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+    }
+
+    #[test]
+    fn github_annotations() {
+        let diff = TextDiff::from_lines("a\nb\nc\n", "a\nB\nc\n");
+        assert_eq!(
+            github(&diff, 0, "README.md".into()),
+            "::error file=README.md,line=2,endLine=2,title=README.md is out of date::-b%0A+B%0A\n"
+        );
+    }
+
+    #[test]
+    fn github_annotation_properties_are_escaped() {
+        let diff = TextDiff::from_lines("a\n", "b\n");
+        assert_eq!(
+            github(&diff, 0, "docs/a,b:%.md".into()),
+            "::error file=docs/a%2Cb%3A%25.md,line=1,endLine=1,title=docs/a%2Cb%3A%25.md is out of date::-a%0A+b%0A\n"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Documented features in the style of the `document-features` crate.
+//!
+//! Lines starting with `##` document the feature that follows them and lines
+//! starting with `#!` are free text placed between the features.
+//!
+//! ```toml
+//! [features]
+//! default = ["std"]
+//! ## Enables the standard library.
+//! std = []
+//! #! ### Experimental features
+//! ## Enables the unstable API.
+//! unstable = []
+//! ```
+
+use std::fs;
+
+use anyhow::{Context as _, Result};
+use cargo_metadata::Package;
+use regex_macro::regex;
+
+/// An entry in the `[features]` table.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Item {
+    /// A feature and its documentation.
+    Feature { name: String, doc: String },
+    /// Free text between the features.
+    Text(String),
+}
+
+/// Reads the documented features from the package manifest.
+pub fn load(pkg: &Package) -> Result<Vec<Item>> {
+    let manifest = fs::read_to_string(&pkg.manifest_path)
+        .with_context(|| format!("failed to read from `{}`", pkg.manifest_path))?;
+    Ok(parse(&manifest))
+}
+
+/// Parses the documented features from the manifest text.
+///
+/// The `default` feature is skipped.
+pub fn parse(manifest: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut doc = Vec::new();
+    let mut text = Vec::new();
+    let mut in_features = false;
+
+    for line in manifest.lines() {
+        let line = line.trim();
+        if let Some(c) = regex!(r"^\[\s*([^\]]*?)\s*\]").captures(line) {
+            in_features = &c[1] == "features";
+            continue;
+        }
+        if !in_features {
+            continue;
+        }
+        if let Some(t) = line.strip_prefix("#!") {
+            text.push(t.strip_prefix(' ').unwrap_or(t).to_owned());
+        } else if let Some(d) = line.strip_prefix("##") {
+            doc.push(d.strip_prefix(' ').unwrap_or(d).to_owned());
+        } else if let Some(c) = regex!(r#"^"?([\w\-.]+)"?\s*="#).captures(line) {
+            if !text.is_empty() {
+                items.push(Item::Text(text.join("\n")));
+                text.clear();
+            }
+            let doc = std::mem::take(&mut doc).join("\n");
+            if &c[1] != "default" {
+                items.push(Item::Feature {
+                    name: c[1].to_owned(),
+                    doc,
+                });
+            }
+        }
+    }
+    if !text.is_empty() {
+        items.push(Item::Text(text.join("\n")));
+    }
+    items
+}
+
+/// Renders the features as Markdown tables separated by the free text.
+pub fn table(items: &[Item], default: &[String]) -> String {
+    let mut out = String::new();
+    let mut in_table = false;
+    for item in items {
+        match item {
+            Item::Feature { name, doc } => {
+                if !in_table {
+                    if !out.is_empty() {
+                        out.push('\n');
+                    }
+                    out.push_str("| Feature | Default | Description |\n");
+                    out.push_str("| ------- | ------- | ----------- |\n");
+                    in_table = true;
+                }
+                let default = if default.contains(name) { "yes" } else { "" };
+                let doc = doc
+                    .lines()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .replace('|', "\\|");
+                out.push_str(&format!("| `{name}` | {default} | {doc} |\n"));
+            }
+            Item::Text(text) => {
+                if !out.is_empty() {
+                    out.push('\n');
+                }
+                out.push_str(text);
+                out.push('\n');
+                in_table = false;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "demo"
+
+[features]
+default = ["std"]
+## Enables the standard library.
+std = []
+#! ### Experimental features
+#!
+#! These may change.
+
+## Enables the unstable API,
+## use with care.
+unstable = [
+    "std",
+]
+undocumented = []
+
+[dependencies]
+## Not a feature.
+serde = "1"
+"#;
+
+    #[test]
+    fn parse_features() {
+        assert_eq!(
+            parse(MANIFEST),
+            vec![
+                Item::Feature {
+                    name: "std".into(),
+                    doc: "Enables the standard library.".into()
+                },
+                Item::Text("### Experimental features\n\nThese may change.".into()),
+                Item::Feature {
+                    name: "unstable".into(),
+                    doc: "Enables the unstable API,\nuse with care.".into()
+                },
+                Item::Feature {
+                    name: "undocumented".into(),
+                    doc: "".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn render_table() {
+        assert_eq!(
+            table(&parse(MANIFEST), &["std".into()]),
+            "\
+| Feature | Default | Description |
+| ------- | ------- | ----------- |
+| `std` | yes | Enables the standard library. |
+
+### Experimental features
+
+These may change.
+
+| Feature | Default | Description |
+| ------- | ------- | ----------- |
+| `unstable` |  | Enables the unstable API, use with care. |
+| `undocumented` |  |  |
+"
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! The standard filters available to templates.
+
+use upon::Value;
+
+/// A filter and its documentation, as shown by `cargo onedoc filters`.
+pub struct Filter {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+}
+
+/// All the filters registered by [`register`].
+pub const FILTERS: &[Filter] = &[
+    Filter {
+        name: "trim_prefix",
+        usage: r#"{{ s | trim_prefix: "prefix" }}"#,
+        description: "Removes all leading occurrences of the prefix.",
+    },
+    Filter {
+        name: "trim_suffix",
+        usage: r#"{{ s | trim_suffix: "suffix" }}"#,
+        description: "Removes all trailing occurrences of the suffix.",
+    },
+    Filter {
+        name: "replace",
+        usage: r#"{{ s | replace: "from", "to" }}"#,
+        description: "Replaces all occurrences of a string with another.",
+    },
+    Filter {
+        name: "lower",
+        usage: "{{ s | lower }}",
+        description: "Converts the string to lowercase.",
+    },
+    Filter {
+        name: "upper",
+        usage: "{{ s | upper }}",
+        description: "Converts the string to uppercase.",
+    },
+    Filter {
+        name: "slug",
+        usage: "{{ s | slug }}",
+        description: "Converts the string to a lowercase slug separated by `-`.",
+    },
+    Filter {
+        name: "join",
+        usage: r#"{{ list | join: ", " }}"#,
+        description: "Joins a list of strings, numbers or booleans with a separator.",
+    },
+    Filter {
+        name: "default",
+        usage: r#"{{ value | default: "fallback" }}"#,
+        description: "Returns the fallback if the value is none or an empty string.",
+    },
+    Filter {
+        name: "indent",
+        usage: "{{ s | indent: 4 }}",
+        description: "Indents every non-empty line except the first by a number of spaces.",
+    },
+    Filter {
+        name: "wrap",
+        usage: "{{ s | wrap: 80 }}",
+        description: "Wraps each paragraph of the string at a line width.",
+    },
+    Filter {
+        name: "markdown_escape",
+        usage: "{{ s | markdown_escape }}",
+        description: "Escapes Markdown special characters.",
+    },
+    Filter {
+        name: "url_encode",
+        usage: "{{ s | url_encode }}",
+        description: "Percent-encodes the string for use in a URL.",
+    },
+    Filter {
+        name: "major",
+        usage: "{{ version | major }}",
+        description: "Returns the major component of a version, e.g. `1` for `1.70.0`.",
+    },
+    Filter {
+        name: "minor",
+        usage: "{{ version | minor }}",
+        description: "Returns the minor component of a version, e.g. `70` for `1.70.0`.",
+    },
+    Filter {
+        name: "first_sentence",
+        usage: "{{ s | first_sentence }}",
+        description: "Returns the first sentence of the string.",
+    },
+];
+
+/// Registers the standard filters with the engine.
+pub fn register(engine: &mut upon::Engine<'_>) {
+    engine.add_filter("trim_prefix", trim_prefix);
+    engine.add_filter("trim_suffix", trim_suffix);
+    engine.add_filter("replace", replace);
+    engine.add_filter("lower", str::to_lowercase);
+    engine.add_filter("upper", str::to_uppercase);
+    engine.add_filter("slug", slug);
+    engine.add_filter("join", join);
+    engine.add_filter("default", default);
+    engine.add_filter("indent", indent);
+    engine.add_filter("wrap", wrap);
+    engine.add_filter("markdown_escape", markdown_escape);
+    engine.add_filter("url_encode", url_encode);
+    engine.add_filter("major", major);
+    engine.add_filter("minor", minor);
+    engine.add_filter("first_sentence", first_sentence);
+}
+
+fn trim_prefix(s: &str, prefix: String) -> String {
+    s.trim_start_matches(&*prefix).to_owned()
+}
+
+fn trim_suffix(s: &str, suffix: String) -> String {
+    s.trim_end_matches(&*suffix).to_owned()
+}
+
+fn replace(s: &str, from: String, to: String) -> String {
+    s.replace(&from, &to)
+}
+
+fn slug(s: &str) -> String {
+    let mut slug = String::new();
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_owned()
+}
+
+fn join(list: &[Value], sep: String) -> Result<String, String> {
+    let mut items = Vec::new();
+    for value in list {
+        let item = match value {
+            Value::String(s) => s.clone(),
+            Value::Integer(n) => n.to_string(),
+            Value::Float(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return Err("join only supports strings, numbers and booleans".into()),
+        };
+        items.push(item);
+    }
+    Ok(items.join(&sep))
+}
+
+fn default(value: Value, fallback: Value) -> Value {
+    match value {
+        Value::None => fallback,
+        Value::String(s) if s.is_empty() => fallback,
+        value => value,
+    }
+}
+
+fn indent(s: &str, width: i64) -> String {
+    let pad = " ".repeat(width.max(0) as usize);
+    let mut lines = s.lines();
+    let mut indented = lines.next().unwrap_or_default().to_owned();
+    for line in lines {
+        indented.push('\n');
+        if !line.is_empty() {
+            indented.push_str(&pad);
+        }
+        indented.push_str(line);
+    }
+    indented
+}
+
+fn wrap(s: &str, width: i64) -> String {
+    let width = width.max(1) as usize;
+    let mut paragraphs = Vec::new();
+    for paragraph in s.split("\n\n") {
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+        paragraphs.push(lines.join("\n"));
+    }
+    paragraphs.join("\n\n")
+}
+
+fn markdown_escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~' | '#'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Percent-encodes the characters that are not allowed in a URL path segment.
+pub fn url_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for c in s.chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' | '.' | '~' => encoded.push(c),
+            c => {
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    encoded.push_str(&format!("%{b:02X}"));
+                }
+            }
+        }
+    }
+    encoded
+}
+
+/// Returns a numeric component of a version, ignoring any leading operator
+/// like `^` or `>=`.
+fn version_part(s: &str, i: usize) -> Result<i64, String> {
+    s.trim_start_matches(|c: char| !c.is_ascii_digit())
+        .split(['.', '-', '+'])
+        .nth(i)
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(|| format!("invalid version `{s}`"))
+}
+
+fn major(s: &str) -> Result<i64, String> {
+    version_part(s, 0)
+}
+
+fn minor(s: &str) -> Result<i64, String> {
+    version_part(s, 1)
+}
+
+fn first_sentence(s: &str) -> String {
+    let s = s.trim();
+    let end = s
+        .match_indices(['.', '!', '?'])
+        .map(|(i, _)| i + 1)
+        .find(|&i| s[i..].is_empty() || s[i..].starts_with(char::is_whitespace))
+        .unwrap_or(s.len());
+    s[..end].split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, values: &Value) -> Result<String, upon::Error> {
+        let mut engine = upon::Engine::new();
+        register(&mut engine);
+        engine.compile(source)?.render_from(values).to_string()
+    }
+
+    #[test]
+    fn all_filters_are_registered() {
+        let values = upon::value! {
+            s: "Hello, World. More",
+            list: ["a", "b"],
+            value: Value::None,
+            version: "1.70.0",
+        };
+        for filter in FILTERS {
+            assert!(filter.usage.contains(filter.name), "{}", filter.name);
+            if let Err(err) = render(filter.usage, &values) {
+                panic!("{}: {:#}", filter.name, err);
+            }
+        }
+    }
+
+    #[test]
+    fn trim() {
+        assert_eq!(
+            trim_prefix("https://github.com/a/b", "https://github.com/".into()),
+            "a/b"
+        );
+        assert_eq!(trim_suffix("name.git", ".git".into()), "name");
+    }
+
+    #[test]
+    fn replace_lower_upper() {
+        assert_eq!(replace("a-b-c", "-".into(), "_".into()), "a_b_c");
+        let values = upon::value! { s: "Hello" };
+        assert_eq!(render("{{ s | lower }}", &values).unwrap(), "hello");
+        assert_eq!(render("{{ s | upper }}", &values).unwrap(), "HELLO");
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slug("Hello, World!"), "hello-world");
+        assert_eq!(slug("  cargo_onedoc 1.0 "), "cargo-onedoc-1-0");
+        assert_eq!(slug("foo - bar"), "foo-bar");
+    }
+
+    #[test]
+    fn joins() {
+        let list = vec![Value::from("a"), Value::from(1_i64), Value::from(true)];
+        assert_eq!(join(&list, ", ".into()).unwrap(), "a, 1, true");
+        assert!(join(&[Value::None], ", ".into()).is_err());
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(default(Value::None, "x".into()), Value::from("x"));
+        assert_eq!(default("".into(), "x".into()), Value::from("x"));
+        assert_eq!(default("a".into(), "x".into()), Value::from("a"));
+    }
+
+    #[test]
+    fn indents() {
+        assert_eq!(indent("a\n\nb\nc", 2), "a\n\n  b\n  c");
+    }
+
+    #[test]
+    fn wraps() {
+        assert_eq!(
+            wrap("one two three four\n\nfive six", 9),
+            "one two\nthree\nfour\n\nfive six"
+        );
+    }
+
+    #[test]
+    fn markdown_escapes() {
+        assert_eq!(markdown_escape("a_b *c* [d]"), r"a\_b \*c\* \[d\]");
+    }
+
+    #[test]
+    fn url_encodes() {
+        assert_eq!(url_encode("MIT OR Apache-2.0"), "MIT%20OR%20Apache-2.0");
+        assert_eq!(url_encode("1.70+"), "1.70%2B");
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(major("1.70.0"), Ok(1));
+        assert_eq!(minor("1.70.0"), Ok(70));
+        assert_eq!(minor("^0.7"), Ok(7));
+        assert!(minor("1").is_err());
+    }
+
+    #[test]
+    fn first_sentences() {
+        assert_eq!(
+            first_sentence("Generate docs. Version 1.0 is\nhere."),
+            "Generate docs."
+        );
+        assert_eq!(first_sentence("No period"), "No period");
+        assert_eq!(first_sentence("Uses v1.0 now. More."), "Uses v1.0 now.");
+    }
+}
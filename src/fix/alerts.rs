@@ -0,0 +1,303 @@
+use anyhow::Result;
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag};
+use regex_macro::regex;
+
+use crate::config::Alerts;
+use crate::render;
+
+/// The supported alert kinds and the labels used for them.
+const KINDS: &[(&str, &str)] = &[
+    ("Note", "NOTE"),
+    ("Tip", "TIP"),
+    ("Important", "IMPORTANT"),
+    ("Warning", "WARNING"),
+    ("Caution", "CAUTION"),
+];
+
+const WARNING_START: &str = r#"<div class="warning">"#;
+const WARNING_END: &str = "</div>";
+
+/// Splits rustdoc warning blocks into start and end tags around their parsed
+/// contents.
+///
+/// The contents of a warning block written on the same lines as the tags are
+/// part of the raw HTML, this parses them as Markdown so that fixes like
+/// resolving intra-doc links also apply to them. This must run before those
+/// fixes and [`fix`] must run after them.
+pub fn prepare(events: Vec<Event>) -> Vec<Event> {
+    let mut prepared = Vec::new();
+    let mut in_warning = false;
+    for event in events {
+        match event {
+            Event::Html(CowStr::Borrowed(html))
+                if !in_warning && regex!(r#"^\s*<div class="warning">"#).is_match(html) =>
+            {
+                let rest = html.trim().trim_start_matches(WARNING_START);
+                prepared.push(Event::Html(CowStr::Borrowed(WARNING_START)));
+                match rest.strip_suffix(WARNING_END) {
+                    Some(text) => {
+                        push_raw(&mut prepared, text);
+                        prepared.push(Event::Html(CowStr::Borrowed(WARNING_END)));
+                    }
+                    None => {
+                        push_raw(&mut prepared, rest);
+                        in_warning = true;
+                    }
+                }
+            }
+            Event::Html(CowStr::Borrowed(html)) if in_warning && html.contains(WARNING_END) => {
+                push_raw(&mut prepared, html.trim().trim_end_matches(WARNING_END));
+                prepared.push(Event::Html(CowStr::Borrowed(WARNING_END)));
+                in_warning = false;
+            }
+            Event::Html(CowStr::Borrowed(html)) if in_warning => push_raw(&mut prepared, html),
+            event => prepared.push(event),
+        }
+    }
+    prepared
+}
+
+/// Converts rustdoc warning blocks and note paragraphs to alerts.
+///
+/// The following are converted:
+///
+/// - `<div class="warning">...</div>` blocks, split by [`prepare`].
+/// - Paragraphs starting with a bold label, e.g. `**Note:** ...`.
+pub fn fix(alerts: Alerts, events: Vec<Event>) -> Result<Vec<Event>> {
+    let mut iter = events.into_iter();
+    let mut events = Vec::new();
+    while let Some(event) = iter.next() {
+        match event {
+            Event::Html(html) if html.as_ref() == WARNING_START => {
+                let mut inner = Vec::new();
+                for event in iter.by_ref() {
+                    match event {
+                        Event::Html(html) if html.as_ref() == WARNING_END => break,
+                        event => inner.push(event),
+                    }
+                }
+                push_alert(
+                    &mut events,
+                    wrap(alerts, Some("Warning"), "WARNING", inner)?,
+                );
+            }
+            Event::Start(Tag::Paragraph) => {
+                let mut inner = vec![event];
+                for event in iter.by_ref() {
+                    let end = matches!(event, Event::End(Tag::Paragraph));
+                    inner.push(event);
+                    if end {
+                        break;
+                    }
+                }
+                match label(&inner) {
+                    Some(kind) => {
+                        // The paragraph already has a label for block quotes
+                        let inner = match alerts {
+                            Alerts::Github => strip_label(inner),
+                            Alerts::Blockquote => inner,
+                        };
+                        push_alert(&mut events, wrap(alerts, None, kind, inner)?)
+                    }
+                    None => events.extend(inner),
+                }
+            }
+            event => events.push(event),
+        }
+    }
+    Ok(events)
+}
+
+/// Pushes a rendered alert, separating it from preceding raw HTML like another
+/// alert so that the block quotes aren't merged.
+fn push_alert<'a>(events: &mut Vec<Event<'a>>, alert: Event<'a>) {
+    if matches!(events.last(), Some(Event::Html(_))) {
+        events.push(Event::Html(CowStr::Borrowed("\n")));
+    }
+    events.push(alert);
+}
+
+/// Parses the Markdown inside a raw HTML block and pushes the events.
+fn push_raw<'a>(events: &mut Vec<Event<'a>>, text: &'a str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        events.extend(Parser::new_ext(text, Options::all()));
+    }
+}
+
+/// Returns the alert kind if the paragraph starts with a bold label.
+fn label(events: &[Event]) -> Option<&'static str> {
+    match events {
+        [Event::Start(Tag::Paragraph), Event::Start(Tag::Strong), Event::Text(text), Event::End(Tag::Strong), rest @ ..] =>
+        {
+            let (name, colon) = match text.strip_suffix(':') {
+                Some(name) => (name, true),
+                None => (text.as_ref(), false),
+            };
+            let colon = colon || matches!(rest.first(), Some(Event::Text(t)) if t.starts_with(':'));
+            if !colon {
+                return None;
+            }
+            KINDS
+                .iter()
+                .find(|(label, _)| *label == name)
+                .map(|(_, kind)| *kind)
+        }
+        _ => None,
+    }
+}
+
+/// Removes the bold label from the start of the paragraph.
+fn strip_label(events: Vec<Event>) -> Vec<Event> {
+    let mut iter = events.into_iter();
+    let mut events: Vec<_> = iter.by_ref().take(1).collect();
+    let mut rest = iter.skip(3).peekable();
+    if let Some(Event::Text(text)) = rest.peek() {
+        let text = text.trim_start_matches(':').trim_start().to_owned();
+        rest.next();
+        if !text.is_empty() {
+            events.push(Event::Text(CowStr::Boxed(text.into())));
+        }
+    }
+    events.extend(rest);
+    events
+}
+
+/// Renders the events as a block quote with an alert marker.
+///
+/// If a label is given it is prepended to the block quote when not rendering
+/// GitHub alerts. The block quote is rendered directly to Markdown because the GitHub alert
+/// marker must be on the first line of the block quote.
+fn wrap<'a>(
+    alerts: Alerts,
+    label: Option<&str>,
+    kind: &str,
+    inner: Vec<Event<'a>>,
+) -> Result<Event<'a>> {
+    let mut iter = inner.into_iter().peekable();
+    let mut events = Vec::new();
+    if let (Alerts::Blockquote, Some(label)) = (alerts, label) {
+        let label = vec![
+            Event::Start(Tag::Strong),
+            Event::Text(CowStr::Boxed(format!("{label}:").into())),
+            Event::End(Tag::Strong),
+            Event::Text(CowStr::Borrowed(" ")),
+        ];
+        match iter.peek() {
+            Some(Event::Start(Tag::Paragraph)) => {
+                events.push(iter.next().unwrap());
+                events.extend(label);
+            }
+            _ => {
+                events.push(Event::Start(Tag::Paragraph));
+                events.extend(label);
+                events.push(Event::End(Tag::Paragraph));
+            }
+        }
+    }
+    events.extend(iter);
+
+    let mut quote = String::new();
+    if alerts == Alerts::Github {
+        quote.push_str(&format!("> [!{kind}]\n"));
+    }
+    for line in render::to_cmark(events)?.lines() {
+        match line {
+            "" => quote.push_str(">\n"),
+            line => quote.push_str(&format!("> {line}\n")),
+        }
+    }
+    Ok(Event::Html(CowStr::Boxed(quote.into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use crate::fix;
+
+    fn convert(alerts: Alerts, text: &str) -> String {
+        let events = prepare(Parser::new_ext(text, Options::all()).collect());
+        render::to_cmark(fix(alerts, events).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn warning_div() {
+        let text = "<div class=\"warning\">\n\nBe **careful**.\n\n</div>\n";
+        assert_eq!(
+            convert(Alerts::Github, text),
+            "> [!WARNING]\n> Be **careful**.\n"
+        );
+        assert_eq!(
+            convert(Alerts::Blockquote, text),
+            "> **Warning:** Be **careful**.\n"
+        );
+    }
+
+    #[test]
+    fn warning_div_single_line() {
+        let text = "<div class=\"warning\">Be careful.</div>\n";
+        assert_eq!(
+            convert(Alerts::Github, text),
+            "> [!WARNING]\n> Be careful.\n"
+        );
+    }
+
+    #[test]
+    fn note_paragraph() {
+        let text = "**Note:** This is a note.\n";
+        assert_eq!(
+            convert(Alerts::Github, text),
+            "> [!NOTE]\n> This is a note.\n"
+        );
+        assert_eq!(
+            convert(Alerts::Blockquote, text),
+            "> **Note:** This is a note.\n"
+        );
+    }
+
+    #[test]
+    fn warning_div_single_line_markdown() {
+        let text = "<div class=\"warning\">Be **careful**.</div>\n";
+        assert_eq!(
+            convert(Alerts::Blockquote, text),
+            "> **Warning:** Be **careful**.\n"
+        );
+    }
+
+    #[test]
+    fn links_in_alerts() {
+        let config = BTreeMap::from([("String".to_owned(), "https://string".to_owned())]);
+        for text in [
+            "**Note:** See [`String`] for details.\n",
+            "<div class=\"warning\">See [`String`] for details.</div>\n",
+            "<div class=\"warning\">\n\nSee [`String`] for details.\n\n</div>\n",
+        ] {
+            let events = prepare(Parser::new_ext(text, Options::all()).collect());
+            let events = fix::doc_links(&config, &mut Default::default(), events);
+            let rendered = render::to_cmark(fix(Alerts::Github, events).unwrap()).unwrap();
+            // The link reference is replaced with the URL when appending links
+            assert!(
+                rendered.ends_with("> See [`String`](string) for details.\n"),
+                "{rendered}"
+            );
+        }
+    }
+
+    #[test]
+    fn adjacent_alerts() {
+        let text = "**Note:** One.\n\n<div class=\"warning\">Two.</div>\n";
+        assert_eq!(
+            convert(Alerts::Github, text),
+            "> [!NOTE]\n> One.\n\n> [!WARNING]\n> Two.\n"
+        );
+    }
+
+    #[test]
+    fn bold_paragraph_is_unchanged() {
+        let text = "**Bold** text.\n";
+        assert_eq!(convert(Alerts::Github, text), "**Bold** text.");
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use camino::{Utf8Component, Utf8Path as Path, Utf8PathBuf as PathBuf};
+use pulldown_cmark::{Event, LinkType, Parser, Tag};
+use regex_macro::regex;
+
+/// Fixes links between input files.
+///
+/// Links to other input files are rewritten to in-document anchors. A link
+/// with a fragment keeps the fragment, otherwise it points at the anchor of the
+/// first heading in the linked file.
+pub fn fix<'a>(
+    anchors: &BTreeMap<PathBuf, String>,
+    dir: &Path,
+    events: Vec<Event<'a>>,
+) -> Vec<Event<'a>> {
+    let mut events = events;
+    for event in &mut events {
+        if let Event::Start(Tag::Link(LinkType::Inline, dst, _))
+        | Event::End(Tag::Link(LinkType::Inline, dst, _)) = event
+        {
+            if regex!(r"^(#|(?:[a-z+]+:)?//)").is_match(dst) {
+                continue;
+            }
+            let (path, fragment) = dst.split_once('#').unwrap_or((dst, ""));
+            if let Some(anchor) = anchors.get(&normalize(&dir.join(path))) {
+                let fragment = if fragment.is_empty() {
+                    anchor
+                } else {
+                    fragment
+                };
+                *dst = format!("#{fragment}").into();
+            }
+        }
+    }
+    events
+}
+
+/// Returns the text of every heading in the Markdown, in order.
+pub fn titles(text: &str) -> Vec<String> {
+    let mut titles = Vec::new();
+    let mut title: Option<String> = None;
+    for event in Parser::new(text) {
+        match event {
+            Event::Start(Tag::Heading(..)) => title = Some(String::new()),
+            Event::End(Tag::Heading(..)) => titles.extend(title.take()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(title) = title.as_mut() {
+                    title.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(title) = title.as_mut() {
+                    title.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    titles
+}
+
+/// Lexically removes `.` and `..` components from the path.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Utf8Component::CurDir => {}
+            Utf8Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pulldown_cmark::{Options, Parser};
+
+    use crate::render;
+
+    #[test]
+    fn links_between_inputs() {
+        let anchors = BTreeMap::from([
+            (PathBuf::from("/book/intro.md"), "introduction".to_owned()),
+            (PathBuf::from("/book/guide/usage.md"), "usage".to_owned()),
+        ]);
+        let text = "\
+See [the intro](../intro.md), [usage](./usage.md#running) and [other](other.md).
+";
+        let events = Parser::new_ext(text, Options::all()).collect();
+        let events = fix(&anchors, Path::new("/book/guide"), events);
+        assert_eq!(
+            render::to_cmark(events).unwrap(),
+            "See [the intro](#introduction), [usage](#running) and [other](other.md)."
+        );
+    }
+
+    #[test]
+    fn headings() {
+        assert_eq!(
+            titles("Some text.\n\n## The `title`\n\n# Other\n"),
+            ["The title", "Other"]
+        );
+        assert!(titles("No headings.").is_empty());
+    }
+}
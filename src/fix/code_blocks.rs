@@ -2,7 +2,9 @@ use anyhow::{anyhow, Result};
 use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag};
 
 /// Fixes code blocks.
-pub fn fix(events: Vec<Event>) -> Result<Vec<Event>> {
+///
+/// If `strip_hidden` is false then `#` prefixed lines are kept.
+pub fn fix(strip_hidden: bool, events: Vec<Event>) -> Result<Vec<Event>> {
     let mut iter = events.into_iter();
     let mut events = Vec::new();
     while let Some(event) = iter.next() {
@@ -13,9 +15,10 @@ pub fn fix(events: Vec<Event>) -> Result<Vec<Event>> {
 
                 loop {
                     match iter.next().unwrap() {
-                        Event::Text(code) => {
+                        Event::Text(code) if strip_hidden => {
                             events.push(Event::Text(fix_code_block(code)));
                         }
+                        Event::Text(code) => events.push(Event::Text(code)),
                         Event::End(Tag::CodeBlock(_)) => {
                             events.push(Event::End(tag));
                             break;
@@ -1,75 +1,161 @@
+use std::collections::BTreeMap;
+
 use pulldown_cmark::{CowStr, Event, LinkType, Tag};
-use regex_macro::regex;
 
-use crate::{Context, Links};
+use crate::target::Target;
+use crate::Links;
 
 /// Fixes intra-doc links.
-pub fn fix<'a>(ctx: &Context, links: &mut Links, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+pub fn fix<'a>(
+    config: &BTreeMap<String, String>,
+    links: &mut Links,
+    events: Vec<Event<'a>>,
+) -> Vec<Event<'a>> {
     let mut iter = events.into_iter().peekable();
     let mut events = Vec::new();
 
     while let Some(event) = iter.next() {
         match event {
             Event::Text(text) if text.as_ref() == "[" => {
-                let mut local = Vec::new();
-                loop {
-                    match iter.next().unwrap() {
-                        Event::Text(text) if text.as_ref() == "]" => break,
-                        event => local.push(event),
+                let local = match group(&mut iter) {
+                    Ok(local) => local,
+                    Err(local) => {
+                        events.push(Event::Text(text));
+                        events.extend(local);
+                        continue;
                     }
-                }
+                };
 
                 match &*local {
-                    &[Event::Code(CowStr::Borrowed(text))] => {
-                        match ctx.config.links.get(text).cloned() {
-                            Some(dest) => {
-                                let link_ref = link_ref(text);
-
-                                let links = links.entry(link_ref.clone()).or_insert_with(Vec::new);
-                                let i = match links.iter().position(|u| *u == dest) {
-                                    Some(i) => i,
-                                    None => {
-                                        let i = links.len();
-                                        links.push(dest);
-                                        i
-                                    }
-                                };
-                                let actual = if i == 0 {
-                                    link_ref
-                                } else {
-                                    format!("{}-{}", link_ref, i)
-                                };
+                    &[Event::Code(CowStr::Borrowed(text))] => match config.get(text).cloned() {
+                        Some(dest) => {
+                            let link_ref = link_ref(text);
+
+                            let links = links.entry(link_ref.clone()).or_default();
+                            let i = match links.iter().position(|u| *u == dest) {
+                                Some(i) => i,
+                                None => {
+                                    let i = links.len();
+                                    links.push(dest);
+                                    i
+                                }
+                            };
+                            let actual = if i == 0 {
+                                link_ref
+                            } else {
+                                format!("{}-{}", link_ref, i)
+                            };
+
+                            let tag = Tag::Link(
+                                LinkType::Reference,
+                                CowStr::Boxed(actual.into_boxed_str()),
+                                CowStr::Borrowed(""),
+                            );
+                            events.push(Event::Start(tag.clone()));
+                            events.push(Event::Code(CowStr::Borrowed(text)));
+                            events.push(Event::End(tag.clone()));
+                        }
+                        None => {
+                            eprintln!("warn: unprocessed link `{}`", text);
+                            events.extend(local)
+                        }
+                    },
+                    _ => events.extend(local),
+                }
+
+                // Drop the reference of a `[text][ref]` link
+                if let Some(Event::Text(text)) = iter.peek() {
+                    if text.as_ref() == "[" {
+                        let open = iter.next().unwrap();
+                        if let Err(local) = group(&mut iter) {
+                            events.push(open);
+                            events.extend(local);
+                        }
+                    }
+                }
+            }
+            event => events.push(event),
+        }
+    }
+
+    events
+}
+
+/// Keeps intra-doc links so that rustdoc can resolve them.
+///
+/// Unresolved links are parsed as separate `[` and `]` text events which would
+/// be escaped when rendered. Links to code are converted to inline links with
+/// the item path as the destination, which rustdoc also supports, the brackets
+/// of other links are passed through unescaped.
+pub fn keep(events: Vec<Event>) -> Vec<Event> {
+    let mut iter = events.into_iter().peekable();
+    let mut events = Vec::new();
+
+    while let Some(event) = iter.next() {
+        match event {
+            Event::Text(text) if text.as_ref() == "[" => {
+                let local = match group(&mut iter) {
+                    Ok(local) => local,
+                    Err(local) => {
+                        events.push(Event::Text(text));
+                        events.extend(local);
+                        continue;
+                    }
+                };
 
+                let mut dest = match &*local {
+                    [Event::Code(text)] => {
+                        let i = text.find('<').unwrap_or(text.len());
+                        text[..i].to_owned()
+                    }
+                    _ => {
+                        // Pass the brackets through unescaped so that rustdoc
+                        // can still resolve the link
+                        events.push(Event::Html(CowStr::Borrowed("[")));
+                        events.extend(local);
+                        events.push(Event::Html(CowStr::Borrowed("]")));
+                        continue;
+                    }
+                };
+
+                if let Some(Event::Text(text)) = iter.peek() {
+                    if text.as_ref() == "[" {
+                        let open = iter.next().unwrap();
+                        match group(&mut iter) {
+                            Ok(reference) => {
+                                dest.clear();
+                                for event in reference {
+                                    if let Event::Text(text) | Event::Code(text) = event {
+                                        dest.push_str(&text);
+                                    }
+                                }
+                            }
+                            Err(rest) => {
+                                // Not a reference, so keep the events after the link
                                 let tag = Tag::Link(
-                                    LinkType::Reference,
-                                    CowStr::Boxed(actual.into_boxed_str()),
+                                    LinkType::Inline,
+                                    CowStr::Boxed(dest.into_boxed_str()),
                                     CowStr::Borrowed(""),
                                 );
                                 events.push(Event::Start(tag.clone()));
-                                events.push(Event::Code(CowStr::Borrowed(text)));
-                                events.push(Event::End(tag.clone()));
-                            }
-                            None => {
-                                eprintln!("warn: unprocessed link `{}`", text);
-                                events.extend(local)
+                                events.extend(local);
+                                events.push(Event::End(tag));
+                                events.push(open);
+                                events.extend(rest);
+                                continue;
                             }
                         }
                     }
-                    _ => events.extend(local),
                 }
 
-                match iter.peek() {
-                    Some(Event::Text(text)) if text.as_ref() == "[" => {
-                        iter.next().unwrap();
-                        loop {
-                            match iter.next().unwrap() {
-                                Event::Text(text) if text.as_ref() == "]" => break,
-                                _ => {}
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+                let tag = Tag::Link(
+                    LinkType::Inline,
+                    CowStr::Boxed(dest.into_boxed_str()),
+                    CowStr::Borrowed(""),
+                );
+                events.push(Event::Start(tag.clone()));
+                events.extend(local);
+                events.push(Event::End(tag));
             }
             event => events.push(event),
         }
@@ -78,12 +164,61 @@ pub fn fix<'a>(ctx: &Context, links: &mut Links, events: Vec<Event<'a>>) -> Vec<
     events
 }
 
+/// Collects the events up to the closing `]` of a bracket group.
+///
+/// Returns the collected events as an error if the group is not closed.
+fn group<'a>(iter: &mut impl Iterator<Item = Event<'a>>) -> Result<Vec<Event<'a>>, Vec<Event<'a>>> {
+    let mut local = Vec::new();
+    for event in iter {
+        match event {
+            Event::Text(text) if text.as_ref() == "]" => return Ok(local),
+            event => local.push(event),
+        }
+    }
+    Err(local)
+}
+
 fn link_ref(text: &str) -> String {
     let text = match text.find('<') {
         Some(i) => &text[..i],
         None => text,
     };
-    regex!(r"[^\w\- ]")
-        .replace_all(&text.to_ascii_lowercase().replace(' ', "-"), "")
-        .into_owned()
+    Target::Github.anchor(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pulldown_cmark::{Options, Parser};
+
+    #[test]
+    fn unclosed_bracket() {
+        let events: Vec<_> = Parser::new_ext("An [ unclosed bracket.", Options::all()).collect();
+        let fixed = fix(&BTreeMap::new(), &mut Links::new(), events.clone());
+        assert_eq!(fixed, events);
+    }
+
+    #[test]
+    fn unclosed_reference() {
+        let events: Vec<_> = Parser::new_ext("See [`Vec`][a", Options::all()).collect();
+        let links = BTreeMap::from([("Vec".to_owned(), "https://vec".to_owned())]);
+        let fixed = render(fix(&links, &mut Links::new(), events.clone()));
+        assert!(fixed.starts_with("See [`Vec`]") && fixed.ends_with("\\[a"));
+        assert_eq!(render(keep(events)), "See [`Vec`](Vec)\\[a");
+    }
+
+    #[test]
+    fn keep_links() {
+        let text = "See [Vec], [`String`], [the map][HashMap] and [`new`][Vec::new].";
+        let events: Vec<_> = Parser::new_ext(text, Options::all()).collect();
+        assert_eq!(
+            render(keep(events)),
+            "See [Vec], [`String`](String), [the map][HashMap] and [`new`](Vec::new)."
+        );
+    }
+
+    fn render(events: Vec<Event>) -> String {
+        crate::render::to_cmark(events).unwrap()
+    }
 }
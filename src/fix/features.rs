@@ -0,0 +1,47 @@
+use pulldown_cmark::{Event, Options, Parser};
+
+const PLACEHOLDER: &str = "<!-- onedoc:features -->";
+
+/// Replaces the `<!-- onedoc:features -->` placeholder with the features table.
+///
+/// Only HTML comments are replaced, a placeholder inside code is left as is.
+pub fn fix(text: &str, table: &str) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    for (event, range) in Parser::new_ext(text, Options::all()).into_offset_iter() {
+        let Event::Html(html) = event else {
+            continue;
+        };
+        if html.trim() != PLACEHOLDER {
+            continue;
+        }
+        result.push_str(&text[last..range.start]);
+        result.push_str(table.trim_end());
+        if text[range.clone()].ends_with('\n') {
+            result.push('\n');
+        }
+        last = range.end;
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholder() {
+        let text = "# Features\n\n<!-- onedoc:features -->\n\nMore\n";
+        assert_eq!(
+            fix(text, "| a | b |\n|---|---|\n"),
+            "# Features\n\n| a | b |\n|---|---|\n\nMore\n"
+        );
+    }
+
+    #[test]
+    fn placeholder_in_code_is_ignored() {
+        let text = "```\n<!-- onedoc:features -->\n```\n\nUse `<!-- onedoc:features -->`.\n";
+        assert_eq!(fix(text, "| a | b |\n"), text);
+    }
+}
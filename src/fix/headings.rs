@@ -1,13 +1,16 @@
 use pulldown_cmark::{Event, Tag};
 
-/// Increases each heading level by one.
-pub fn fix(events: Vec<Event>) -> Vec<Event> {
+/// Increases each heading level by one plus the given depth.
+///
+/// Headings are never increased past level six.
+pub fn fix(depth: usize, events: Vec<Event>) -> Vec<Event> {
     let mut iter = events.into_iter();
     let mut events = Vec::new();
     while let Some(event) = iter.next() {
         match event {
             Event::Start(Tag::Heading(level, frag, classes)) => {
-                let tag = Tag::Heading((level as usize + 1).try_into().unwrap(), frag, classes);
+                let level = (level as usize + 1 + depth).min(6);
+                let tag = Tag::Heading(level.try_into().unwrap(), frag, classes);
                 events.push(Event::Start(tag.clone()));
                 loop {
                     match iter.next().unwrap() {
@@ -0,0 +1,27 @@
+use pulldown_cmark::Event;
+
+/// Removes raw HTML comments.
+pub fn fix(events: Vec<Event>) -> Vec<Event> {
+    let mut iter = events.into_iter();
+    let mut events = Vec::new();
+    while let Some(event) = iter.next() {
+        match event {
+            Event::Html(html) if html.trim_start().starts_with("<!--") => {
+                // The comment may span multiple HTML events
+                let mut html = html;
+                while !html.contains("-->") {
+                    match iter.next() {
+                        Some(Event::Html(h)) => html = h,
+                        Some(event) => {
+                            events.push(event);
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+            }
+            event => events.push(event),
+        }
+    }
+    events
+}
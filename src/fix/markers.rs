@@ -0,0 +1,161 @@
+use anyhow::{bail, Result};
+use camino::Utf8Path as Path;
+use pulldown_cmark::{Event, Options, Parser};
+
+/// Removes or unwraps regions of the Markdown marked for specific outputs.
+///
+/// The following markers are recognized:
+///
+/// - `<!-- onedoc:skip-start -->` and `<!-- onedoc:skip-end -->`: everything
+///   between the markers is removed.
+/// - `<!-- onedoc:only ... -->`: the contents of the comment are included.
+///
+/// The opening marker can optionally be followed by a list of output file names
+/// that it applies to, e.g. `<!-- onedoc:skip-start README.md -->`. If no names
+/// are given then the marker applies to every output. For `only` markers the
+/// names must be followed by a newline, a single line marker such as
+/// `<!-- onedoc:only [badge](x) -->` applies to every output.
+pub fn fix(text: &str, output: &Path) -> Result<String> {
+    let mut result = String::new();
+    let mut last = 0;
+    let mut skip_start: Option<Option<usize>> = None;
+
+    let mut iter = Parser::new_ext(text, Options::all()).into_offset_iter();
+    while let Some((event, range)) = iter.next() {
+        let Event::Html(html) = event else {
+            continue;
+        };
+        let Some(directive) = html.trim_start().strip_prefix("<!-- onedoc:") else {
+            continue;
+        };
+        let name = directive.split_whitespace().next().unwrap_or_default();
+        let name = name.split("-->").next().unwrap();
+
+        if name == "skip-start" {
+            let rest = &directive["skip-start".len()..];
+            if skip_start.is_some() {
+                bail!("nested `onedoc:skip-start` marker");
+            }
+            let args = rest.trim_end().trim_end_matches("-->");
+            if applies(args, output) {
+                skip_start = Some(Some(range.start));
+            } else {
+                // Only remove the marker itself
+                skip_start = Some(None);
+                result.push_str(&text[last..range.start]);
+                last = range.end;
+            }
+        } else if name == "skip-end" {
+            let start = match skip_start.take() {
+                Some(Some(start)) => start,
+                Some(None) => range.start,
+                None => bail!("`onedoc:skip-end` marker without a matching `onedoc:skip-start`"),
+            };
+            result.push_str(&text[last..start]);
+            last = range.end;
+        } else if name == "only" {
+            // The comment may span multiple HTML events, find the end.
+            let mut range = range;
+            while !text[range.clone()].contains("-->") {
+                match iter.next() {
+                    Some((Event::Html(_), r)) => range.end = r.end,
+                    _ => bail!("unterminated `onedoc:only` marker"),
+                }
+            }
+            let comment = &text[range.clone()];
+            let body = &comment[comment.find("onedoc:only").unwrap() + "onedoc:only".len()..];
+            let body = &body[..body.find("-->").unwrap()];
+            let (args, inner) = body.split_once('\n').unwrap_or(("", body.trim()));
+            if inner.trim().is_empty() {
+                bail!("empty `onedoc:only` marker");
+            }
+            result.push_str(&text[last..range.start]);
+            if applies(args, output) {
+                result.push_str(inner.trim_end());
+                result.push('\n');
+            }
+            last = range.end;
+        }
+    }
+
+    if skip_start.is_some() {
+        bail!("`onedoc:skip-start` marker without a matching `onedoc:skip-end`");
+    }
+
+    result.push_str(&text[last..]);
+    Ok(result)
+}
+
+/// Returns true if the marker arguments apply to the given output.
+fn applies(args: &str, output: &Path) -> bool {
+    let mut names = args.split_whitespace().peekable();
+    names.peek().is_none() || names.any(|name| Some(name) == output.file_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_region() {
+        let text = "a\n\n<!-- onedoc:skip-start -->\n\nb\n\n<!-- onedoc:skip-end -->\n\nc\n";
+        assert_eq!(fix(text, "README.md".into()).unwrap(), "a\n\n\nc\n");
+    }
+
+    #[test]
+    fn skip_region_for_other_output() {
+        let text =
+            "a\n\n<!-- onedoc:skip-start OTHER.md -->\n\nb\n\n<!-- onedoc:skip-end -->\n\nc\n";
+        let result = fix(text, "README.md".into()).unwrap();
+        assert!(result.contains("b\n"));
+        assert!(!result.contains("onedoc:"));
+    }
+
+    #[test]
+    fn only_region() {
+        let text = "a\n\n<!-- onedoc:only\n[![badge](x.svg)](y)\n-->\n\nc\n";
+        assert_eq!(
+            fix(text, "README.md".into()).unwrap(),
+            "a\n\n[![badge](x.svg)](y)\n\nc\n"
+        );
+    }
+
+    #[test]
+    fn only_region_for_other_output() {
+        let text = "a\n\n<!-- onedoc:only OTHER.md\nb\n-->\n\nc\n";
+        assert_eq!(fix(text, "README.md".into()).unwrap(), "a\n\n\nc\n");
+    }
+
+    #[test]
+    fn single_line_only_region() {
+        let text = "a\n\n<!-- onedoc:only [badge](x) -->\n\nc\n";
+        assert_eq!(
+            fix(text, "README.md".into()).unwrap(),
+            "a\n\n[badge](x)\n\nc\n"
+        );
+    }
+
+    #[test]
+    fn empty_only_region() {
+        let text = "a\n\n<!-- onedoc:only README.md\n-->\n";
+        assert!(fix(text, "README.md".into()).is_err());
+    }
+
+    #[test]
+    fn longer_directives_are_ignored() {
+        let text = "<!-- onedoc:onlyx -->\n\n<!-- onedoc:skip-endx -->\n";
+        assert_eq!(fix(text, "README.md".into()).unwrap(), text);
+    }
+
+    #[test]
+    fn markers_in_code_blocks_are_ignored() {
+        let text = "```\n<!-- onedoc:skip-start -->\n```\n";
+        assert_eq!(fix(text, "README.md".into()).unwrap(), text);
+    }
+
+    #[test]
+    fn unterminated_skip() {
+        let text = "<!-- onedoc:skip-start -->\n\nb\n";
+        assert!(fix(text, "README.md".into()).is_err());
+    }
+}
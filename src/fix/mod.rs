@@ -1,11 +1,27 @@
+mod alerts;
+mod chapter_links;
 mod code_blocks;
 mod doc_links;
+mod features;
 mod headings;
+mod html_comments;
+mod markers;
 mod rel_links;
+pub mod reverse;
+mod sections;
 mod summary;
 
+pub use crate::fix::alerts::fix as alerts;
+pub use crate::fix::alerts::prepare as prepare_alerts;
+pub use crate::fix::chapter_links::fix as chapter_links;
+pub use crate::fix::chapter_links::titles as chapter_titles;
 pub use crate::fix::code_blocks::fix as code_blocks;
 pub use crate::fix::doc_links::fix as doc_links;
+pub use crate::fix::doc_links::keep as keep_doc_links;
+pub use crate::fix::features::fix as features;
 pub use crate::fix::headings::fix as headings;
+pub use crate::fix::html_comments::fix as html_comments;
+pub use crate::fix::markers::fix as markers;
 pub use crate::fix::rel_links::fix as rel_links;
+pub use crate::fix::sections::fix as sections;
 pub use crate::fix::summary::fix as summary;
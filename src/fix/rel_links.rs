@@ -1,10 +1,10 @@
+use std::collections::BTreeMap;
+
 use pulldown_cmark::{Event, LinkType, Tag};
 use regex_macro::regex;
 
-use crate::Context;
-
 /// Fixes relative file links.
-pub fn fix<'a>(ctx: &Context, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+pub fn fix<'a>(links: &BTreeMap<String, String>, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
     let mut iter = events.into_iter();
     let mut events = Vec::new();
     while let Some(event) = iter.next() {
@@ -15,7 +15,7 @@ pub fn fix<'a>(ctx: &Context, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
                 let i = dst.find('#').unwrap_or(dst.len());
                 let dst_no_frag: &str = &dst[..i];
                 let fragment: &str = &dst[i..];
-                match ctx.config.links.get(dst_no_frag).cloned() {
+                match links.get(dst_no_frag).cloned() {
                     Some(new_dst) => {
                         events.push(Event::Start(Tag::Link(
                             LinkType::Inline,
@@ -36,7 +36,11 @@ pub fn fix<'a>(ctx: &Context, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
                     }
                     None => {
                         eprintln!("warn: unprocessed link `{}`", dst_no_frag);
-                        events.push(iter.next().unwrap());
+                        events.push(Event::Start(Tag::Link(
+                            LinkType::Inline,
+                            dst.clone(),
+                            title,
+                        )));
                     }
                 }
             }
@@ -45,3 +49,22 @@ pub fn fix<'a>(ctx: &Context, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
     }
     events
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pulldown_cmark::{Options, Parser};
+
+    use crate::render;
+
+    #[test]
+    fn unprocessed_link_is_kept() {
+        let events = Parser::new_ext("Back to [intro](../README.md).", Options::all()).collect();
+        let events = fix(&BTreeMap::new(), events);
+        assert_eq!(
+            render::to_cmark(events).unwrap(),
+            "Back to [intro](../README.md)."
+        );
+    }
+}
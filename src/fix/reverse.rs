@@ -0,0 +1,100 @@
+//! The inverse of the fixes applied when generating Markdown.
+
+use std::collections::BTreeMap;
+
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, LinkType, Tag};
+
+/// Decreases each heading level by one.
+///
+/// Level one headings are left as is.
+pub fn headings(events: Vec<Event>) -> Vec<Event> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::Heading(level, frag, classes)) => {
+                Event::Start(Tag::Heading(decrease(level as usize), frag, classes))
+            }
+            Event::End(Tag::Heading(level, frag, classes)) => {
+                Event::End(Tag::Heading(decrease(level as usize), frag, classes))
+            }
+            event => event,
+        })
+        .collect()
+}
+
+fn decrease(level: usize) -> pulldown_cmark::HeadingLevel {
+    level.saturating_sub(1).max(1).try_into().unwrap()
+}
+
+/// Makes `rust` code blocks bare code blocks.
+pub fn code_blocks(events: Vec<Event>) -> Vec<Event> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(attr)))
+                if attr.as_ref() == "rust" =>
+            {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(CowStr::Borrowed(""))))
+            }
+            Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(attr))) if attr.as_ref() == "rust" => {
+                Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(CowStr::Borrowed(""))))
+            }
+            event => event,
+        })
+        .collect()
+}
+
+/// Turns links to known URLs back into intra-doc links.
+///
+/// The link destination is replaced with the name from the links config, the
+/// caller is expected to collapse links where the text matches the name.
+pub fn doc_links<'a>(links: &BTreeMap<&str, &str>, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::Link(_, dst, title)) if links.contains_key(dst.as_ref()) => {
+                let name = links[dst.as_ref()].to_owned();
+                Event::Start(Tag::Link(LinkType::Inline, name.into(), title))
+            }
+            Event::End(Tag::Link(_, dst, title)) if links.contains_key(dst.as_ref()) => {
+                let name = links[dst.as_ref()].to_owned();
+                Event::End(Tag::Link(LinkType::Inline, name.into(), title))
+            }
+            event => event,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pulldown_cmark::{Options, Parser};
+
+    use crate::render;
+
+    fn reverse(text: &str) -> String {
+        let mut events = Vec::from_iter(Parser::new_ext(text, Options::all()));
+        events = headings(events);
+        events = code_blocks(events);
+        let links = BTreeMap::from([("https://example.com/String.html", "String")]);
+        events = doc_links(&links, events);
+        render::to_cmark(events).unwrap()
+    }
+
+    #[test]
+    fn headings_and_code_blocks() {
+        assert_eq!(
+            reverse("## Usage\n\n```rust\nrun();\n```\n\n```sh\nls\n```\n"),
+            "# Usage\n\n```\nrun();\n```\n\n```sh\nls\n```"
+        );
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            reverse("A [`String`](https://example.com/String.html).\n"),
+            "A [`String`](String)."
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use pulldown_cmark::{Event, HeadingLevel, Tag};
+
+use crate::target::{Anchors, Target};
+
+/// A top-level section of the processed Markdown.
+#[derive(Debug)]
+pub struct Section<'a> {
+    /// The heading text with all Markdown code stripped out.
+    pub title: String,
+    /// The anchor for the heading, calculated according to the target and
+    /// suffixed with `-1`, `-2` etc. if an earlier heading has the same one.
+    pub slug: String,
+    /// The heading level.
+    pub level: HeadingLevel,
+    /// The heading events.
+    pub heading: Vec<Event<'a>>,
+    /// The events following the heading up until the next section.
+    pub body: Vec<Event<'a>>,
+}
+
+impl Section<'_> {
+    /// The slug as a template identifier.
+    ///
+    /// `-` is replaced by `_`, and the key is prefixed with `_` if it would
+    /// otherwise start with a digit or be empty.
+    pub fn key(&self) -> String {
+        let key = self.slug.replace('-', "_");
+        match key.chars().next() {
+            Some(c) if !c.is_ascii_digit() => key,
+            _ => format!("_{key}"),
+        }
+    }
+}
+
+/// Split the events into sections by top-level heading.
+///
+/// The top-level is the shallowest heading level found in the events. Any
+/// events before the first top-level heading are not part of any section.
+pub fn fix<'a>(target: Target, events: &[Event<'a>]) -> Vec<Section<'a>> {
+    let top = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Start(Tag::Heading(level, ..)) => Some(*level),
+            _ => None,
+        })
+        .min();
+
+    let Some(top) = top else {
+        return Vec::new();
+    };
+
+    // Every heading is counted so that duplicate slugs match the anchors
+    let mut anchors = Anchors::new(target);
+    let mut sections: Vec<Section> = Vec::new();
+    let mut iter = events.iter().cloned();
+    while let Some(event) = iter.next() {
+        match event {
+            Event::Start(Tag::Heading(level, ..)) => {
+                let mut heading = vec![event];
+                let mut title = String::new();
+                for event in iter.by_ref() {
+                    match &event {
+                        Event::Text(s) | Event::Code(s) => title.push_str(s),
+                        Event::SoftBreak | Event::HardBreak => title.push(' '),
+                        _ => {}
+                    }
+                    let end = matches!(event, Event::End(Tag::Heading(..)));
+                    heading.push(event);
+                    if end {
+                        break;
+                    }
+                }
+                let slug = anchors.next(&title);
+                if level == top {
+                    sections.push(Section {
+                        slug,
+                        title,
+                        level,
+                        heading,
+                        body: Vec::new(),
+                    });
+                } else if let Some(section) = sections.last_mut() {
+                    section.body.extend(heading);
+                }
+            }
+            event => {
+                if let Some(section) = sections.last_mut() {
+                    section.body.push(event);
+                }
+            }
+        }
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pulldown_cmark::Parser;
+
+    #[test]
+    fn split_by_top_level_heading() {
+        let text = "Intro\n\n## Usage\n\nRun it.\n\n### Details\n\nMore.\n\n## `Installation` guide\n\nInstall it.\n";
+        let events: Vec<_> = Parser::new(text).collect();
+        let sections = fix(Target::Github, &events);
+
+        let titles: Vec<_> = sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Usage", "Installation guide"]);
+        let slugs: Vec<_> = sections.iter().map(|s| s.slug.as_str()).collect();
+        assert_eq!(slugs, ["usage", "installation-guide"]);
+        assert!(sections[0]
+            .body
+            .iter()
+            .any(|e| matches!(e, Event::Start(Tag::Heading(HeadingLevel::H3, ..)))));
+    }
+
+    #[test]
+    fn duplicate_slugs() {
+        let text = "## Usage\n\n### Example\n\n## Example\n\n## Usage\n";
+        let events: Vec<_> = Parser::new(text).collect();
+        let slugs: Vec<_> = fix(Target::Github, &events)
+            .into_iter()
+            .map(|s| s.slug)
+            .collect();
+        assert_eq!(slugs, ["usage", "example-1", "usage-1"]);
+    }
+
+    #[test]
+    fn keys() {
+        let text = "## 1.0 Release\n\nGetting\nstarted\n---\n\n## !\n";
+        let events: Vec<_> = Parser::new(text).collect();
+        let sections = fix(Target::Github, &events);
+        assert_eq!(sections[1].title, "Getting started");
+        let keys: Vec<_> = sections.iter().map(|s| s.key()).collect();
+        assert_eq!(keys, ["_10_release", "getting_started", "_"]);
+    }
+
+    #[test]
+    fn no_headings() {
+        let events: Vec<_> = Parser::new("Just a paragraph.\n").collect();
+        assert!(fix(Target::Github, &events).is_empty());
+    }
+}
@@ -0,0 +1,329 @@
+//! Generate a starter config for a package.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+use cargo_metadata::{Metadata, Package};
+use pulldown_cmark::{Event, Options, Parser};
+use regex_macro::regex;
+
+use crate::{reverse, templates};
+
+/// The starter config.
+#[derive(Debug, PartialEq, Eq)]
+struct Starter {
+    /// The input file, relative to the config directory.
+    input: String,
+    /// Whether the input is Markdown that is included as the crate docs.
+    include_str: bool,
+    /// The output file, relative to the config directory.
+    output: String,
+    /// The template file, relative to the config directory.
+    template: Option<String>,
+    /// Whether to inject into the existing output file.
+    inject: bool,
+    badges: Vec<&'static str>,
+    links: BTreeMap<String, String>,
+}
+
+/// Writes a starter config and template for the package.
+///
+/// The config is written to `onedoc.toml` in the workspace directory, or to a
+/// `[package.metadata.onedoc]` table in the package manifest if the workspace
+/// has more than one package, since `onedoc.toml` is shared by every package.
+///
+/// With `include_str` the crate docs are moved to `docs/crate.md` and replaced
+/// with `#![doc = include_str!(..)]` in the crate, the input is marked as
+/// rustdoc so it is processed like the crate docs. Existing files are only
+/// overwritten with `force`.
+pub fn run(metadata: &Metadata, pkg: &Package, force: bool, include_str: bool) -> Result<()> {
+    let package_dir = pkg.manifest_path.parent().unwrap();
+    let in_manifest = metadata.workspace_members.len() > 1;
+    // Paths in the package table are relative to the package directory
+    let root = if in_manifest {
+        package_dir
+    } else {
+        &metadata.workspace_root
+    };
+    let rel = |p: &Path| {
+        p.strip_prefix(root)
+            .unwrap_or(p)
+            .as_str()
+            .replace('\\', "/")
+    };
+
+    let is = |t: &cargo_metadata::Target, kind: &str| t.kind.iter().any(|k| k == kind);
+    let lib = pkg
+        .targets
+        .iter()
+        .find(|t| is(t, "lib") || is(t, "proc-macro"));
+    let Some(target) = lib.or_else(|| pkg.targets.iter().find(|t| is(t, "bin"))) else {
+        bail!("package `{}` has no library or binary target", pkg.name);
+    };
+    let source = &target.src_path;
+    let docs = crate::get_module_comment(source)
+        .with_context(|| format!("failed to read from `{}`", source))?;
+
+    // New files, which are only overwritten with `force`
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    // Existing files that are updated
+    let mut edits: Vec<(PathBuf, String)> = Vec::new();
+    let mut notes = Vec::new();
+
+    let output = package_dir.join(pkg.readme.as_deref().unwrap_or(Path::new("README.md")));
+    let current = fs::read_to_string(&output).unwrap_or_default();
+    let inject = current.contains("<!-- onedoc:begin ");
+    if !current.is_empty() && !inject && !current.contains("Generated by cargo-onedoc") {
+        notes.push(format!(
+            "`{}` will be overwritten when generating docs, add `<!-- onedoc:begin NAME -->` \
+             markers to it and set `mode = \"inject\"` to keep it",
+            rel(&output)
+        ));
+    }
+
+    let mut template = None;
+    if !inject {
+        let path = root.join("docs/README_TEMPLATE.md");
+        let name = if lib.is_some() { "library" } else { "binary" };
+        files.push((path.clone(), templates::builtin(name)?.to_owned()));
+        template = Some(rel(&path));
+    }
+
+    let input = if include_str {
+        let path = root.join("docs/crate.md");
+        files.push((path.clone(), format!("{}\n", docs.trim_end())));
+        let depth = source
+            .strip_prefix(root)
+            .map_or(1, |p| p.components().count() - 1);
+        let current = fs::read_to_string(source)
+            .with_context(|| format!("failed to read from `{}`", source))?;
+        if regex!(r"(?m)^#!\[doc\s*=\s*include_str!").is_match(&current) {
+            bail!("`{}` already includes its crate docs", rel(source));
+        }
+        let attr = format!(
+            "#![doc = include_str!(\"{}docs/crate.md\")]\n",
+            "../".repeat(depth)
+        );
+        edits.push((source.clone(), reverse::replace_docs(&current, &attr)));
+        rel(&path)
+    } else {
+        rel(source)
+    };
+
+    let github = pkg
+        .repository
+        .as_deref()
+        .is_some_and(|r| r.starts_with("https://github.com/"));
+    let badges = [
+        Some("crates-io"),
+        lib.map(|_| "docs-rs"),
+        pkg.license.as_ref().map(|_| "license"),
+        pkg.rust_version.as_ref().map(|_| "msrv"),
+        github.then_some("ci"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    let ident = pkg.name.replace('-', "_");
+    let links = intra_doc_links(&docs)
+        .into_iter()
+        .map(|item| {
+            let url = link_url(&pkg.name, &ident, &item);
+            (item, url)
+        })
+        .collect();
+
+    let starter = Starter {
+        input,
+        include_str,
+        output: rel(&output),
+        template,
+        inject,
+        badges,
+        links,
+    };
+    if in_manifest {
+        let manifest = fs::read_to_string(&pkg.manifest_path)
+            .with_context(|| format!("failed to read from `{}`", pkg.manifest_path))?;
+        if manifest.contains("[package.metadata.onedoc") {
+            bail!(
+                "`{}` already has a `[package.metadata.onedoc]` table",
+                pkg.manifest_path
+            );
+        }
+        let toml = starter.to_toml("package.metadata.onedoc.");
+        let manifest = format!("{}\n\n{}", manifest.trim_end(), toml);
+        edits.insert(0, (pkg.manifest_path.clone(), manifest));
+    } else {
+        files.insert(0, (root.join("onedoc.toml"), starter.to_toml("")));
+    }
+
+    if !force {
+        for (path, _) in &files {
+            if path.exists() {
+                bail!(
+                    "`{}` already exists, use `--force` to overwrite it",
+                    rel(path)
+                );
+            }
+        }
+    }
+    for (path, contents) in &files {
+        fs::create_dir_all(path.parent().unwrap())
+            .with_context(|| format!("failed to create directory for `{}`", path))?;
+        fs::write(path, contents).with_context(|| format!("failed to write to `{}`", path))?;
+        println!("Created {}", rel(path));
+    }
+    for (path, contents) in &edits {
+        fs::write(path, contents).with_context(|| format!("failed to write to `{}`", path))?;
+        println!("Updated {}", rel(path));
+    }
+    for note in notes {
+        println!("note: {}", note);
+    }
+    Ok(())
+}
+
+impl Starter {
+    /// Returns the config as TOML, with the tables nested under the prefix.
+    fn to_toml(&self, prefix: &str) -> String {
+        let quote = |s: &str| toml::Value::from(s).to_string();
+        let mut out = String::new();
+        out.push_str(&format!("[[{prefix}doc]]\n"));
+        if self.include_str {
+            out.push_str(&format!(
+                "input = {{ path = {}, kind = \"rustdoc\" }}\n",
+                quote(&self.input)
+            ));
+        } else {
+            out.push_str(&format!("input = {}\n", quote(&self.input)));
+        }
+        out.push_str(&format!("output = {}\n", quote(&self.output)));
+        if let Some(template) = &self.template {
+            out.push_str(&format!("template = {}\n", quote(template)));
+        }
+        if self.inject {
+            out.push_str("mode = \"inject\"\n");
+        }
+
+        let badges: Vec<_> = self.badges.iter().map(|b| quote(b)).collect();
+        out.push_str(&format!("\n[{prefix}badges]\n"));
+        out.push_str(&format!("include = [{}]\n", badges.join(", ")));
+
+        if !self.links.is_empty() {
+            out.push_str(
+                "\n# Intra-doc links found in the crate docs, check that these are correct\n",
+            );
+            out.push_str(&format!("[{prefix}links]\n"));
+            for (item, url) in &self.links {
+                out.push_str(&format!("{} = {}\n", quote(item), quote(url)));
+            }
+        }
+        out
+    }
+}
+
+/// Returns the unique intra-doc links like ``[`Item`]`` in the crate docs.
+fn intra_doc_links(docs: &str) -> Vec<String> {
+    let events: Vec<_> = Parser::new_ext(docs, Options::all()).collect();
+    let mut links = Vec::new();
+    for window in events.windows(3) {
+        if let [Event::Text(open), Event::Code(item), Event::Text(close)] = window {
+            if open.ends_with('[') && close.starts_with(']') && !links.contains(&item.to_string()) {
+                links.push(item.to_string());
+            }
+        }
+    }
+    links
+}
+
+/// Returns a best guess at the URL for an intra-doc link.
+///
+/// Items from the standard library link to its docs, everything else links to
+/// a search on docs.rs since the kind of item isn't known.
+fn link_url(name: &str, ident: &str, item: &str) -> String {
+    let item = item.trim_end_matches("()").trim_end_matches('!');
+    let (path, last) = item.rsplit_once("::").unwrap_or(("", item));
+    let krate = path.split("::").next().unwrap_or_default();
+    if matches!(krate, "std" | "core" | "alloc") {
+        format!("https://doc.rust-lang.org/stable/std/?search={}", last)
+    } else {
+        format!("https://docs.rs/{}/latest/{}/?search={}", name, ident, last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_intra_doc_links() {
+        let docs =
+            "See [`Config`] and [`std::fmt::Display`], not [`Linked`](https://example.com)\n\
+                    or `[not a link]`. Again [`Config`].";
+        assert_eq!(
+            intra_doc_links(docs),
+            vec!["Config".to_owned(), "std::fmt::Display".to_owned()]
+        );
+    }
+
+    #[test]
+    fn link_urls() {
+        assert_eq!(
+            link_url("my-crate", "my_crate", "Config"),
+            "https://docs.rs/my-crate/latest/my_crate/?search=Config"
+        );
+        assert_eq!(
+            link_url("my-crate", "my_crate", "std::fmt::Display"),
+            "https://doc.rust-lang.org/stable/std/?search=Display"
+        );
+    }
+
+    #[test]
+    fn starter_toml() {
+        let mut starter = Starter {
+            input: "src/lib.rs".into(),
+            include_str: false,
+            output: "README.md".into(),
+            template: Some("docs/README_TEMPLATE.md".into()),
+            inject: false,
+            badges: vec!["crates-io", "docs-rs"],
+            links: BTreeMap::from([("Config".into(), "https://example.com".into())]),
+        };
+        assert_eq!(
+            starter.to_toml(""),
+            r#"[[doc]]
+input = "src/lib.rs"
+output = "README.md"
+template = "docs/README_TEMPLATE.md"
+
+[badges]
+include = ["crates-io", "docs-rs"]
+
+# Intra-doc links found in the crate docs, check that these are correct
+[links]
+"Config" = "https://example.com"
+"#
+        );
+
+        starter.input = "docs/crate.md".into();
+        starter.include_str = true;
+        let toml = starter.to_toml("");
+        assert!(
+            toml.starts_with("[[doc]]\ninput = { path = \"docs/crate.md\", kind = \"rustdoc\" }\n")
+        );
+        let config: crate::config::Config = toml::from_str(&toml).unwrap();
+        assert!(matches!(
+            config.docs[0].inputs[..],
+            [crate::config::Input::File(_)]
+        ));
+
+        let toml = starter.to_toml("package.metadata.onedoc.");
+        assert!(toml.starts_with("[[package.metadata.onedoc.doc]]\n"));
+        assert!(toml.contains("\n[package.metadata.onedoc.badges]\n"));
+        assert!(toml.contains("\n[package.metadata.onedoc.links]\n"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+use regex_macro::regex;
+
+/// A named region of a file.
+#[derive(Debug, PartialEq, Eq)]
+struct Region<'a> {
+    name: &'a str,
+    /// The byte range of the contents between the markers.
+    range: Range<usize>,
+}
+
+/// Replace the marked regions in `current` with the matching regions in
+/// `rendered`.
+///
+/// Regions are marked using `<!-- onedoc:begin NAME -->` and
+/// `<!-- onedoc:end NAME -->` on their own lines. Every region in `current`
+/// must have a matching region in `rendered`, the other way around is not
+/// required.
+pub fn inject(current: &str, rendered: &str) -> Result<String> {
+    let targets = regions(current)?;
+    if targets.is_empty() {
+        bail!("no `onedoc:begin` markers found");
+    }
+    let sources: HashMap<_, _> = regions(rendered)?
+        .into_iter()
+        .map(|r| (r.name, rendered[r.range].trim()))
+        .collect();
+
+    let mut result = String::new();
+    let mut last = 0;
+    for Region { name, range } in targets {
+        let Some(contents) = sources.get(name) else {
+            bail!("no contents rendered for region `{}`", name);
+        };
+        result.push_str(&current[last..range.start]);
+        result.push('\n');
+        if !contents.is_empty() {
+            result.push_str(contents);
+            result.push_str("\n\n");
+        }
+        last = range.end;
+    }
+    result.push_str(&current[last..]);
+    Ok(result)
+}
+
+/// Find all the named regions in the text.
+fn regions(text: &str) -> Result<Vec<Region<'_>>> {
+    let re = regex!(r"(?m)^<!-- onedoc:(begin|end) ([\w.\-]+) -->[ \t]*\r?$");
+
+    let mut regions = Vec::new();
+    let mut open: Option<(&str, usize)> = None;
+    for c in re.captures_iter(text) {
+        let m = c.get(0).unwrap();
+        let name = c.get(2).unwrap().as_str();
+        match (&c[1], open) {
+            ("begin", None) => open = Some((name, m.end())),
+            ("begin", Some((other, _))) => {
+                bail!("region `{}` begins inside region `{}`", name, other)
+            }
+            ("end", Some((other, start))) if other == name => {
+                regions.push(Region {
+                    name,
+                    range: start..m.start(),
+                });
+                open = None;
+            }
+            ("end", _) => bail!("unexpected end of region `{}`", name),
+            _ => unreachable!(),
+        }
+    }
+    if let Some((name, _)) = open {
+        bail!("region `{}` is not closed", name);
+    }
+    Ok(regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inject_region() {
+        let current = "# Title\n\nHand written.\n\n<!-- onedoc:begin usage -->\nold\n<!-- onedoc:end usage -->\n\nFooter.\n";
+        let rendered = "<!-- onedoc:begin usage -->\nnew\n<!-- onedoc:end usage -->\n<!-- onedoc:begin other -->\nignored\n<!-- onedoc:end other -->\n";
+        assert_eq!(
+            inject(current, rendered).unwrap(),
+            "# Title\n\nHand written.\n\n<!-- onedoc:begin usage -->\nnew\n\n<!-- onedoc:end usage -->\n\nFooter.\n"
+        );
+    }
+
+    #[test]
+    fn inject_is_idempotent() {
+        let current = "<!-- onedoc:begin a -->\n<!-- onedoc:end a -->\n";
+        let rendered = "<!-- onedoc:begin a -->\ncontents\n<!-- onedoc:end a -->\n";
+        let once = inject(current, rendered).unwrap();
+        assert_eq!(inject(&once, rendered).unwrap(), once);
+    }
+
+    #[test]
+    fn inject_missing_region() {
+        let current = "<!-- onedoc:begin a -->\n<!-- onedoc:end a -->\n";
+        let err = inject(current, "").unwrap_err();
+        assert_eq!(err.to_string(), "no contents rendered for region `a`");
+    }
+
+    #[test]
+    fn unclosed_region() {
+        let err = regions("<!-- onedoc:begin a -->\n").unwrap_err();
+        assert_eq!(err.to_string(), "region `a` is not closed");
+    }
+}
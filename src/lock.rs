@@ -0,0 +1,117 @@
+//! The lock file that records how inputs were expanded.
+//!
+//! Glob patterns, directories and books are expanded into files when the
+//! config is loaded. Recording the expansion in `onedoc.lock` means that a
+//! change in the matched files shows up in `--check`, even when the generated
+//! output happens to stay the same.
+
+use camino::Utf8Path as Path;
+use serde::Serialize;
+
+use crate::config::{Config, Input};
+
+/// The name of the lock file, next to the package manifest.
+pub const FILE_NAME: &str = "onedoc.lock";
+
+const HEADER: &str = "# This file is generated by cargo-onedoc, do not edit it manually.\n\
+                      # It records the files that glob patterns, directories and books expanded to.\n";
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Lock {
+    #[serde(rename = "doc")]
+    docs: Vec<Entry>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct Entry {
+    /// The output file, relative to the workspace directory.
+    output: String,
+    /// The expanded input files, relative to the workspace directory.
+    files: Vec<String>,
+}
+
+impl Lock {
+    /// Returns the lock for the docs that have inputs that need expanding.
+    ///
+    /// Paths are relative to the workspace directory so that the lock is the
+    /// same on every machine.
+    pub fn new(config: &Config, root: &Path) -> Self {
+        let rel = |p: &Path| {
+            p.strip_prefix(root)
+                .unwrap_or(p)
+                .as_str()
+                .replace('\\', "/")
+        };
+        let docs = config
+            .docs
+            .iter()
+            .filter(|doc| {
+                doc.inputs
+                    .iter()
+                    .any(|i| matches!(i, Input::Glob(_) | Input::Dir(_) | Input::Book(_)))
+            })
+            .map(|doc| Entry {
+                output: rel(&doc.output),
+                files: doc.files.iter().map(|f| rel(&f.path)).collect(),
+            })
+            .collect();
+        Self { docs }
+    }
+
+    /// Whether there is nothing to record.
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Returns the contents of the lock file.
+    pub fn contents(&self) -> String {
+        format!("{}\n{}", HEADER, toml::to_string_pretty(self).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::File;
+
+    #[test]
+    fn records_expanded_inputs() {
+        let mut config: Config = toml::from_str(
+            r#"
+[[doc]]
+input = "src/lib.rs"
+output = "README.md"
+
+[[doc]]
+input = ["src/lib.rs", "docs/*.md"]
+output = "docs/README.md"
+"#,
+        )
+        .unwrap();
+        let root = Path::new("/ws");
+        for doc in &mut config.docs {
+            doc.output = root.join(&doc.output);
+            doc.files = vec![
+                File::from(root.join("src/lib.rs")),
+                File::from(root.join("docs/a.md")),
+            ];
+        }
+        let lock = Lock::new(&config, root);
+        assert_eq!(
+            lock.contents(),
+            format!(
+                "{}\n{}",
+                HEADER,
+                r#"[[doc]]
+output = "docs/README.md"
+files = [
+    "src/lib.rs",
+    "docs/a.md",
+]
+"#
+            )
+        );
+        assert!(Lock::new(&Config::default(), root).is_empty());
+    }
+}
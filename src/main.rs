@@ -1,20 +1,32 @@
+mod badges;
 mod config;
+mod diff;
+mod features;
+mod filters;
 mod fix;
+mod init;
+mod inject;
+mod lock;
+mod mdbook;
+mod metadata;
 mod render;
+mod reverse;
+mod target;
+mod templates;
 
 use std::collections::BTreeMap;
 use std::fs;
 use std::io;
 
 use anyhow::{anyhow, bail, Context as _, Result};
-use camino::Utf8Path as Path;
-use cargo_metadata::Package;
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+use cargo_metadata::{Metadata, Package};
 use clap::Parser as _;
-use pulldown_cmark::HeadingLevel;
 use pulldown_cmark::{Options, Parser};
-use pulldown_cmark_toc as toc;
+use regex_macro::regex;
+use serde::Serialize;
 
-use crate::config::{Config, Doc};
+use crate::config::{Config, Direction, Doc, File, Kind, Mode};
 
 #[derive(Debug, clap::Parser)]
 #[clap(
@@ -31,158 +43,592 @@ enum Cargo {
 #[derive(Debug, Clone, clap::Args)]
 #[command(author, version, about)]
 struct Opt {
-    #[clap(long, short)]
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(long, short, global = true)]
     package: Option<String>,
 
+    /// Generate docs for every package in the workspace.
     #[clap(long)]
+    workspace: bool,
+
+    /// Exclude a package when using `--workspace`, can be given multiple times.
+    #[clap(long, value_name = "SPEC", requires = "workspace")]
+    exclude: Vec<String>,
+
+    #[clap(long, conflicts_with = "stdout")]
     check: bool,
+
+    /// Write the rendered docs to standard output instead of the output files.
+    #[clap(long)]
+    stdout: bool,
+
+    /// Write the rendered doc to this path instead of the configured output.
+    #[clap(long, value_name = "PATH", conflicts_with = "workspace")]
+    output: Option<PathBuf>,
+
+    /// Only generate the doc with this name, can be given multiple times.
+    #[clap(long = "doc", value_name = "NAME")]
+    docs: Vec<String>,
+
+    #[clap(flatten)]
+    diff: diff::Options,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+enum Command {
+    /// Show the configuration for a package.
+    Config {
+        /// Print the fully resolved configuration.
+        #[clap(long, required = true)]
+        print: bool,
+    },
+
+    /// Write a starter config and template for a package.
+    Init {
+        /// Overwrite existing files.
+        #[clap(long)]
+        force: bool,
+
+        /// Move the crate docs to `docs/crate.md` and include them in the crate
+        /// using `#![doc = include_str!(..)]`.
+        #[clap(long)]
+        include_str: bool,
+    },
+
+    /// Run as an mdBook preprocessor.
+    Mdbook {
+        #[clap(subcommand)]
+        command: Option<MdbookCommand>,
+    },
+
+    /// List the filters available to templates.
+    Filters,
+
+    /// List the built-in templates.
+    Template {
+        /// Print the source of a built-in template.
+        #[clap(long, value_name = "NAME")]
+        dump: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+enum MdbookCommand {
+    /// Check whether the preprocessor supports a renderer.
+    Supports { renderer: String },
 }
 
 pub struct Context<'a> {
     check: bool,
+    stdout: bool,
+    headers: bool,
+    diff: &'a diff::Options,
+    metadata: &'a Metadata,
     package: &'a Package,
     config: Config,
 }
+
+/// The outcome of generating a doc.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    UpToDate,
+    Updated,
+}
+
 fn main() -> Result<()> {
-    let Cargo::Command(Opt { check, package }) = Cargo::parse();
-    let metadata = cargo_metadata::MetadataCommand::new().exec()?;
+    let Cargo::Command(opt) = Cargo::parse();
 
-    let pkg = match package {
+    match &opt.command {
+        // Answer the mdBook handshake without loading any metadata
+        Some(Command::Mdbook {
+            command: Some(MdbookCommand::Supports { renderer }),
+        }) => {
+            if !mdbook::supports(renderer) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Mdbook { command: None }) => {
+            let metadata = cargo_metadata::MetadataCommand::new().exec()?;
+            let config = config::load(&metadata, package(&opt, &metadata)?)?;
+            mdbook::run(&config.links)?;
+        }
+        Some(Command::Filters) => {
+            for filter in filters::FILTERS {
+                println!("{:<16} {}", filter.name, filter.description);
+                println!("{:<16} {}", "", filter.usage);
+            }
+        }
+        Some(Command::Template { dump: Some(name) }) => {
+            print!("{}", templates::builtin(name)?);
+        }
+        Some(Command::Template { dump: None }) => {
+            for (name, _) in templates::BUILTINS {
+                println!("builtin:{}", name);
+            }
+        }
+        Some(Command::Config { .. }) => {
+            let metadata = cargo_metadata::MetadataCommand::new().exec()?;
+            let config = config::load(&metadata, package(&opt, &metadata)?)?;
+            print!("{}{}", toml::to_string_pretty(&config)?, config.expanded());
+        }
+        Some(Command::Init { force, include_str }) => {
+            let metadata = cargo_metadata::MetadataCommand::new().exec()?;
+            let pkg = package(&opt, &metadata)?;
+            init::run(&metadata, pkg, *force, *include_str)?;
+        }
+        None if opt.workspace => {
+            let metadata = cargo_metadata::MetadataCommand::new().exec()?;
+            generate_workspace(&opt, &metadata)?;
+        }
+        None => {
+            let metadata = cargo_metadata::MetadataCommand::new().exec()?;
+            generate_package(&opt, &metadata, package(&opt, &metadata)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the package selected using `--package`, or the root package.
+fn package<'a>(opt: &Opt, metadata: &'a Metadata) -> Result<&'a Package> {
+    if opt.workspace {
+        bail!("`--workspace` can only be used when generating docs");
+    }
+    match &opt.package {
         Some(name) => metadata
             .workspace_packages()
             .into_iter()
-            .find(|p| p.name == name)
-            .ok_or_else(|| anyhow!("package `{}` not found in workspace", name))?,
-        None => metadata.root_package().context("no root package")?,
-    };
+            .find(|p| &p.name == name)
+            .ok_or_else(|| anyhow!("package `{}` not found in workspace", name)),
+        None => metadata.root_package().context("no root package"),
+    }
+}
 
-    let config = config::load(&metadata, pkg)?;
-    generate_all(Context {
-        check,
-        package: pkg,
+fn generate_workspace(opt: &Opt, metadata: &Metadata) -> Result<()> {
+    if opt.package.is_some() {
+        bail!("`--package` can't be used with `--workspace`");
+    }
+
+    let (mut updated, mut unchanged, mut failed) = (0, 0, 0);
+    let mut packages = Vec::new();
+    for pkg in metadata.workspace_packages() {
+        if opt.exclude.contains(&pkg.name) {
+            continue;
+        }
+        match load_package(opt, metadata, pkg) {
+            Ok((config, lock_status)) => packages.push((pkg, config, lock_status)),
+            Err(err) => {
+                eprintln!("error: package `{}`: {:#}", pkg.name, err);
+                failed += 1;
+            }
+        }
+    }
+
+    // Separate the docs on stdout if there is more than one across the workspace
+    let headers = packages.iter().map(|(_, c, _)| c.docs.len()).sum::<usize>() > 1;
+    for (pkg, config, lock_status) in packages {
+        match generate_all(context(opt, metadata, pkg, config, headers)) {
+            Ok(Status::Updated) => updated += 1,
+            Ok(Status::UpToDate) if lock_status == Status::Updated => updated += 1,
+            Ok(Status::UpToDate) => unchanged += 1,
+            Err(err) => {
+                eprintln!("error: package `{}`: {:#}", pkg.name, err);
+                failed += 1;
+            }
+        }
+    }
+    eprintln!(
+        "{} updated, {} unchanged, {} failed",
+        updated, unchanged, failed
+    );
+    if failed > 0 {
+        bail!("failed to generate docs for {} package(s)", failed);
+    }
+    Ok(())
+}
+
+fn generate_package(opt: &Opt, metadata: &Metadata, pkg: &Package) -> Result<Status> {
+    let (config, lock_status) = load_package(opt, metadata, pkg)?;
+    let headers = config.docs.len() > 1;
+    let status = generate_all(context(opt, metadata, pkg, config, headers))?;
+    if lock_status == Status::Updated {
+        return Ok(Status::Updated);
+    }
+    Ok(status)
+}
+
+/// Loads the config of the package, updates its lock file and selects the docs
+/// to generate.
+fn load_package(opt: &Opt, metadata: &Metadata, pkg: &Package) -> Result<(Config, Status)> {
+    let mut config = config::load(metadata, pkg)?;
+    let lock_status = generate_lock(opt, metadata, pkg, &config)?;
+
+    if !opt.docs.is_empty() {
+        // When generating a whole workspace not every package needs every doc
+        if !opt.workspace {
+            for name in &opt.docs {
+                if !config.docs.iter().any(|d| d.name.as_ref() == Some(name)) {
+                    bail!("doc `{}` not found in config", name);
+                }
+            }
+        }
+        config
+            .docs
+            .retain(|d| d.name.as_ref().is_some_and(|n| opt.docs.contains(n)));
+    }
+
+    if let Some(output) = &opt.output {
+        match &mut *config.docs {
+            [doc] => doc.output = PathBuf::try_from(std::env::current_dir()?)?.join(output),
+            _ => bail!("`--output` can only be used with a single doc, use `--doc` to select one"),
+        }
+    }
+
+    Ok((config, lock_status))
+}
+
+fn context<'a>(
+    opt: &'a Opt,
+    metadata: &'a Metadata,
+    package: &'a Package,
+    config: Config,
+    headers: bool,
+) -> Context<'a> {
+    Context {
+        check: opt.check,
+        stdout: opt.stdout,
+        headers,
+        diff: &opt.diff,
+        metadata,
+        package,
         config,
-    })
+    }
 }
 
-fn generate_all(ctx: Context<'_>) -> Result<()> {
-    let mut engine = {
-        let mut e = upon::Engine::new();
-        e.add_filter("trim_prefix", |s: &str, p: &str| {
-            s.trim_start_matches(p).to_owned()
-        });
-        e
+/// Records how the inputs of all docs were expanded in the lock file.
+fn generate_lock(opt: &Opt, metadata: &Metadata, pkg: &Package, config: &Config) -> Result<Status> {
+    let lock = lock::Lock::new(config, &metadata.workspace_root);
+    let path = pkg.manifest_path.with_file_name(lock::FILE_NAME);
+    let current = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(err) if err.kind() == io::ErrorKind::NotFound && lock.is_empty() => {
+            return Ok(Status::UpToDate)
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("failed to read `{}`", path))?,
     };
+    let rendered = lock.contents();
 
-    for doc in &ctx.config.docs {
-        generate_doc(&mut engine, &ctx, doc)?;
+    if opt.stdout || current == rendered {
+        Ok(Status::UpToDate)
+    } else if opt.check {
+        print!("{}", diff::render(&opt.diff, &path, &current, &rendered));
+        bail!(
+            "`{}` is out of date, the input files have changed",
+            lock::FILE_NAME
+        );
+    } else {
+        fs::write(&path, rendered).with_context(|| format!("failed to write to `{}`", path))?;
+        println!("{} was updated", lock::FILE_NAME);
+        Ok(Status::Updated)
     }
-    Ok(())
 }
 
-#[derive(Clone, Copy)]
-enum Kind {
-    RustDoc,
-    Markdown,
+fn generate_all(ctx: Context<'_>) -> Result<Status> {
+    let mut engine = templates::Engine::new();
+    if let Some(dir) = &ctx.config.templates {
+        engine
+            .register_dir(dir)
+            .with_context(|| format!("failed to load templates from `{}`", dir))?;
+    }
+
+    let mut status = Status::UpToDate;
+    for doc in &ctx.config.docs {
+        if ctx.stdout && ctx.headers {
+            println!("==> {} <==", &doc.output);
+        }
+        if generate_doc(&mut engine, &ctx, doc)? == Status::Updated {
+            status = Status::Updated;
+        }
+    }
+    Ok(status)
 }
 
 type Links = BTreeMap<String, Vec<String>>;
 
-fn generate_doc(engine: &mut upon::Engine<'_>, ctx: &Context<'_>, doc: &Doc) -> Result<()> {
+fn generate_doc(
+    engine: &mut templates::Engine<'_>,
+    ctx: &Context<'_>,
+    doc: &Doc,
+) -> Result<Status> {
+    let current = match fs::read_to_string(&doc.output) {
+        Ok(c) => c,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).context("failed to read current README")?,
+    };
+
+    let rendered = match doc.direction {
+        Direction::ToMarkdown => generate_markdown(engine, ctx, doc, &current)?,
+        Direction::ToRustdoc => reverse::generate(&ctx.config, doc, &current)?,
+    };
+
+    if ctx.stdout {
+        print!("{}", rendered);
+        Ok(Status::UpToDate)
+    } else if current == rendered {
+        println!("{} is up to date", doc);
+        Ok(Status::UpToDate)
+    } else if ctx.check {
+        print!(
+            "{}",
+            diff::render(ctx.diff, &doc.output, &current, &rendered)
+        );
+        bail!("{} is out of date", doc);
+    } else {
+        fs::write(&doc.output, rendered)
+            .with_context(|| format!("failed to write to `{}`", &doc.output))?;
+        println!("{} was updated", doc);
+        Ok(Status::Updated)
+    }
+}
+
+fn generate_markdown(
+    engine: &mut templates::Engine<'_>,
+    ctx: &Context<'_>,
+    doc: &Doc,
+    current: &str,
+) -> Result<String> {
     // Compile the template
-    let template_name = match &doc.template {
-        Some(path) => {
-            let contents = fs::read_to_string(path)?;
-            engine
-                .add_template(path.to_string(), contents)
-                .map_err(|e| anyhow!("{:#}", e))?;
-            path.to_string()
+    let template_name = match (&doc.template, &doc.template_str) {
+        (_, Some(source)) => {
+            let name = format!("template_str for {}", doc);
+            let dir = ctx
+                .config
+                .templates
+                .as_deref()
+                .unwrap_or(ctx.package.manifest_path.parent().unwrap());
+            engine.add(&name, source, &templates::dir_lookup(dir))?;
+            name
         }
-        None => {
-            let name = "<anonymous>";
-            engine
-                .add_template(name, include_str!("README_TEMPLATE.md"))
-                .map_err(|e| anyhow!("{:#}", e))?;
-            name.to_string()
+        (Some(path), None) if templates::builtin_name(path).is_some() => {
+            add_builtin(engine, templates::builtin_name(path).unwrap())?
+        }
+        (Some(path), None) => {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("failed to read template from `{}`", path))?;
+            // Base templates are looked up in the templates directory, falling
+            // back to the directory of the template
+            let dir = ctx
+                .config
+                .templates
+                .as_deref()
+                .unwrap_or(path.parent().unwrap());
+            engine.add(path.as_str(), &contents, &templates::dir_lookup(dir))?;
+            path.to_string()
         }
+        (None, None) => match doc.mode {
+            Mode::Overwrite => add_builtin(engine, templates::BUILTINS[0].0)?,
+            Mode::Inject => {
+                let name = "<anonymous-inject>";
+                if !engine.contains(name) {
+                    engine.add(
+                        name,
+                        include_str!("INJECT_TEMPLATE.md"),
+                        &templates::builtin_lookup,
+                    )?;
+                }
+                name.to_string()
+            }
+        },
     };
 
     // Load the Markdown to process
     let to_process = {
         let mut items = Vec::new();
-        for input in &doc.inputs {
-            let item = match input.extension() {
-                Some("rs") => {
-                    let kind = Kind::RustDoc;
-                    let text = get_module_comment(input)
-                        .with_context(|| format!("failed to read from `{}`", input))?;
-                    (kind, text)
-                }
-                Some("md") => {
-                    let kind = Kind::Markdown;
-                    let text = fs::read_to_string(input)
-                        .with_context(|| format!("failed to read from `{}`", input))?;
-                    (kind, text)
-                }
-                Some(_) | None => {
-                    bail!("unsupported file extension `{}`", input);
-                }
+        for file in &doc.files {
+            let input = &file.path;
+            let kind = match (file.kind, input.extension()) {
+                (Some(kind), _) => kind,
+                (None, Some("rs")) => Kind::Rustdoc,
+                (None, Some("md")) => Kind::Markdown,
+                (None, _) => bail!(
+                    "unsupported file extension `{}`, set the `kind` of the input",
+                    input
+                ),
             };
+            // The crate docs of Rust files are in the `//!` comments
+            let text = if input.extension() == Some("rs") {
+                get_module_comment(input)
+            } else {
+                fs::read_to_string(input).map_err(Into::into)
+            }
+            .with_context(|| format!("failed to read from `{}`", input))?;
+            let item = (kind, file, text);
             items.push(item);
         }
         items
     };
 
-    let rendered = render(engine, ctx, &template_name, to_process)?;
-
-    let current = match fs::read_to_string(&doc.output) {
-        Ok(c) => c,
-        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
-        Err(err) => return Err(err).context("failed to read current README")?,
-    };
+    let rendered = render(engine, ctx, doc, &template_name, to_process)?;
 
-    if current == rendered {
-        println!("{} is up to date", &doc.output);
-    } else if ctx.check {
-        bail!("{} is out of date", &doc.output);
-    } else {
-        fs::write(&doc.output, rendered)
-            .with_context(|| format!("failed to write to `{}`", &doc.output))?;
-        println!("{} was updated", &doc.output);
+    match doc.mode {
+        Mode::Overwrite => Ok(rendered),
+        Mode::Inject => inject::inject(current, &rendered)
+            .with_context(|| format!("failed to inject into `{}`", &doc.output)),
     }
+}
 
-    Ok(())
+/// Registers a built-in template with the engine, returning its name.
+fn add_builtin(engine: &mut templates::Engine<'_>, name: &str) -> Result<String> {
+    let template_name = format!("builtin:{}", name);
+    if !engine.contains(&template_name) {
+        engine.add(
+            &template_name,
+            templates::builtin(name)?,
+            &templates::builtin_lookup,
+        )?;
+    }
+    Ok(template_name)
 }
 
 fn get_module_comment(path: &Path) -> Result<String> {
     let contents = fs::read_to_string(path)?;
-    let lines: Vec<_> = contents
-        .lines()
-        .take_while(|line| line.starts_with("//!"))
-        .map(|line| line.trim_start_matches("//! ").trim_start_matches("//!"))
-        .collect();
+    let brackets =
+        |line: &str| line.matches('[').count() as isize - line.matches(']').count() as isize;
+    let mut lines = Vec::new();
+    // The number of unclosed brackets of a skipped attribute
+    let mut depth = 0;
+    for line in contents.lines() {
+        if depth > 0 {
+            depth += brackets(line);
+        } else if line.starts_with("//!") {
+            let line = line.trim_start_matches("//! ").trim_start_matches("//!");
+            lines.push(line.to_owned());
+        } else if let Some(c) = regex!(r#"^#!\[doc\s*=\s*"(.*)"\]$"#).captures(line) {
+            let text = c[1].replace("\\n", "\n").replace("\\\"", "\"");
+            lines.extend(text.lines().map(str::to_owned));
+        } else if line.starts_with("#![") {
+            // Skip other attributes, which may span multiple lines. This
+            // includes `cfg_attr(doc, doc = ..)` which is only rendered by
+            // rustdoc.
+            depth = brackets(line);
+        } else if line.trim().is_empty() || (line.starts_with("//") && !line.starts_with("///")) {
+            // Blank lines and comments such as license headers
+        } else {
+            break;
+        }
+    }
     Ok(lines.join("\n"))
 }
 
+/// A section of the processed Markdown as exposed to templates.
+#[derive(Serialize)]
+struct SectionValue {
+    /// The slug as an identifier so that it can be used in a template path.
+    key: String,
+    title: String,
+    slug: String,
+    level: usize,
+    body: String,
+    contents: String,
+}
+
+/// The values available to templates.
+#[derive(Serialize)]
+struct Values<'a> {
+    manifest: serde_json::Value,
+    badges: String,
+    dependencies: Vec<metadata::Dependency>,
+    features: Vec<metadata::Feature>,
+    features_table: String,
+    targets: metadata::Targets,
+    rust_version: Option<String>,
+    license: &'a Option<String>,
+    authors: &'a Vec<String>,
+    keywords: &'a Vec<String>,
+    categories: &'a Vec<String>,
+    workspace: metadata::Workspace,
+    vars: BTreeMap<String, toml::Value>,
+    env: BTreeMap<String, Option<String>>,
+    metadata: Option<&'a serde_json::Value>,
+    summary: String,
+    contents: String,
+    full_contents: String,
+    toc: String,
+    sections: &'a [SectionValue],
+    section: BTreeMap<String, &'a SectionValue>,
+}
+
 fn render(
-    engine: &upon::Engine<'_>,
+    engine: &templates::Engine<'_>,
     ctx: &Context,
+    doc: &Doc,
     template_name: &str,
-    to_process: Vec<(Kind, String)>,
+    to_process: Vec<(Kind, &File, String)>,
 ) -> Result<String> {
     let mut events = Vec::new();
     let mut link_config = Links::new();
+    let links = ctx.config.links(doc);
+
+    let feature_items = features::load(ctx.package)?;
+    let features_table = {
+        let default = ctx.package.features.get("default");
+        features::table(&feature_items, default.map_or(&[], Vec::as_slice))
+    };
+
+    // Remove or unwrap any regions marked for specific outputs
+    let to_process = to_process
+        .into_iter()
+        .map(|(kind, file, text)| Ok((kind, file, fix::markers(&text, &doc.output)?)))
+        .collect::<Result<Vec<_>>>()
+        .context("failed to process markers")?;
+
+    // Replace the features placeholder with the features table
+    let to_process: Vec<_> = to_process
+        .into_iter()
+        .map(|(kind, file, text)| {
+            let text = fix::features(&text, &features_table);
+            (kind, file, text)
+        })
+        .collect();
 
-    for (kind, text) in &to_process {
+    // Links between Markdown inputs point at the first heading of the input.
+    // Every heading is counted in document order so that duplicate headings
+    // get the same anchors as in the table of contents.
+    let mut anchors = BTreeMap::new();
+    let mut unique = target::Anchors::new(doc.target);
+    for (kind, file, text) in &to_process {
+        for (i, title) in fix::chapter_titles(text).iter().enumerate() {
+            let anchor = unique.next(title);
+            if i == 0 && matches!(kind, Kind::Markdown) {
+                anchors.insert(file.path.clone(), anchor);
+            }
+        }
+    }
+
+    for (kind, file, text) in &to_process {
         let mut es = Vec::from_iter(Parser::new_ext(text, Options::all()));
         // common fixes
-        es = fix::headings(es);
+        es = fix::headings(file.depth, es);
+        if doc.target.strip_html_comments() {
+            es = fix::html_comments(es);
+        }
         match kind {
-            Kind::RustDoc => {
-                es = fix::code_blocks(es).context("failed to fix codeblocks")?;
-                es = fix::doc_links(ctx, &mut link_config, es);
+            Kind::Rustdoc => {
+                es = fix::code_blocks(doc.target.strip_hidden_lines(), es)
+                    .context("failed to fix codeblocks")?;
+                es = fix::prepare_alerts(es);
+                if doc.target.rewrite_doc_links() {
+                    es = fix::doc_links(&links, &mut link_config, es);
+                } else {
+                    es = fix::keep_doc_links(es);
+                }
+                es = fix::alerts(doc.alerts(), es).context("failed to fix alerts")?;
             }
             Kind::Markdown => {
-                es = fix::rel_links(ctx, es);
+                es = fix::chapter_links(&anchors, file.path.parent().unwrap(), es);
+                es = fix::rel_links(&links, es);
             }
         }
         events.extend(es);
@@ -191,6 +637,24 @@ fn render(
     // Now render contents as markdown
     let full_contents = render::to_cmark(&events).context("failed to render contents")?;
 
+    let sections = {
+        let mut list = Vec::new();
+        for s in fix::sections(doc.target, &events) {
+            let heading = render::to_cmark(&s.heading).context("failed to render section")?;
+            let body = render::to_cmark(&s.body).context("failed to render section")?;
+            list.push(SectionValue {
+                key: s.key(),
+                title: s.title,
+                slug: s.slug,
+                level: s.level as usize,
+                contents: format!("{heading}\n\n{body}"),
+                body,
+            });
+        }
+        list
+    };
+    let section: BTreeMap<_, _> = sections.iter().map(|s| (s.key.clone(), s)).collect();
+
     let (summary, contents) = {
         let (s, c) = fix::summary(events);
         let summary = render::to_cmark(&s).context("failed to render summary")?;
@@ -198,37 +662,74 @@ fn render(
         (summary, contents)
     };
 
-    let toc = toc::TableOfContents::new(&full_contents)
-        .to_cmark_with_options(toc::Options::default().levels(HeadingLevel::H2..=HeadingLevel::H6));
-
-    let mut rendered = engine
-        .get_template(template_name)
-        .unwrap()
-        .render(upon::value! {
-            manifest: ctx.package,
-            summary: summary,
-            contents: contents,
-            full_contents: full_contents,
-            toc: toc,
-        })
-        .to_string()
-        .map_err(|e| anyhow!("{:#}", e))?;
+    let toc = render::toc(&full_contents, doc.target);
+
+    // `[package.metadata]` is only exposed through the opt-in `metadata` value
+    let mut manifest = serde_json::to_value(ctx.package)?;
+    manifest.as_object_mut().unwrap().remove("metadata");
+
+    let values = upon::to_value(Values {
+        manifest,
+        badges: badges::render(&ctx.config.badges.clone().unwrap_or_default(), ctx.package),
+        dependencies: metadata::dependencies(ctx.metadata, ctx.package),
+        features: metadata::features(ctx.package, &feature_items),
+        features_table,
+        targets: metadata::targets(ctx.package),
+        rust_version: ctx.package.rust_version.as_ref().map(|v| v.to_string()),
+        license: &ctx.package.license,
+        authors: &ctx.package.authors,
+        keywords: &ctx.package.keywords,
+        categories: &ctx.package.categories,
+        workspace: metadata::workspace(ctx.metadata, ctx.package, doc.output.parent().unwrap()),
+        vars: ctx.config.vars(doc),
+        env: ctx.config.env(),
+        metadata: ctx
+            .config
+            .metadata
+            .unwrap_or(false)
+            .then_some(&ctx.package.metadata),
+        summary,
+        contents,
+        full_contents,
+        toc,
+        sections: &sections,
+        section,
+    })
+    .map_err(|e| anyhow!("{}", e))
+    .context("failed to serialize template values")?;
+
+    let mut rendered = engine.render(template_name, &values)?;
 
-    // Append link info
-    if !link_config.is_empty() {
+    // Append link info, when injecting into regions the links are inlined
+    // instead because the end of the output is not part of any region
+    append_links(&mut rendered, link_config, doc.mode == Mode::Inject);
+
+    Ok(rendered)
+}
+
+/// Appends the link reference definitions for the intra-doc links.
+///
+/// If `inline` is true then the links are inlined instead.
+fn append_links(rendered: &mut String, links: Links, inline: bool) {
+    if links.is_empty() {
+        return;
+    }
+    if !inline {
         rendered.push_str("\n\n");
-        for (name, links) in link_config {
-            for (i, u) in links.into_iter().enumerate() {
-                let name = if i == 0 {
-                    name.to_owned()
-                } else {
-                    format!("{}-{}", name, i)
-                };
-                rendered = rendered.replace(&format!("({})", name), &format!("[{}]", name));
+    }
+    for (name, links) in links {
+        for (i, u) in links.into_iter().enumerate() {
+            let name = if i == 0 {
+                name.to_owned()
+            } else {
+                format!("{}-{}", name, i)
+            };
+            if inline {
+                *rendered = rendered.replace(&format!("({})", name), &format!("({})", u));
+            } else {
+                *rendered = rendered.replace(&format!("({})", name), &format!("[{}]", name));
                 rendered.push_str(&format!("[{}]: {}\n", name, u));
             }
         }
     }
-
-    Ok(rendered)
 }
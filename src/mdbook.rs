@@ -0,0 +1,147 @@
+//! Running as an mdBook preprocessor.
+//!
+//! See <https://rust-lang.github.io/mdBook/for_developers/preprocessors.html>
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+
+use anyhow::{Context as _, Result};
+use camino::Utf8Path as Path;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regex_macro::regex;
+use serde_json::Value;
+
+use crate::{fix, get_module_comment, render, Links};
+
+/// Returns whether the renderer is supported.
+///
+/// The preprocessor only outputs Markdown so every renderer is supported.
+pub fn supports(_renderer: &str) -> bool {
+    true
+}
+
+/// Reads the book from stdin, processes each chapter and writes the book to
+/// stdout.
+pub fn run(links: &BTreeMap<String, String>) -> Result<()> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .context("failed to read from stdin")?;
+    let (context, mut book): (Value, Value) =
+        serde_json::from_str(&input).context("failed to parse mdBook input")?;
+
+    let root = context["root"].as_str().unwrap_or(".");
+    let src = context["config"]["book"]["src"].as_str().unwrap_or("src");
+    let src = Path::new(root).join(src);
+
+    if let Some(items) = book["sections"].as_array_mut() {
+        process_items(&src, links, items)?;
+    }
+
+    serde_json::to_writer(io::stdout(), &book).context("failed to write to stdout")?;
+    Ok(())
+}
+
+/// Processes the chapters in the book items and their sub items.
+fn process_items(src: &Path, links: &BTreeMap<String, String>, items: &mut [Value]) -> Result<()> {
+    for item in items {
+        let Some(chapter) = item.get_mut("Chapter") else {
+            continue;
+        };
+        // Draft chapters don't have a source file
+        if let (Some(path), Some(content)) =
+            (chapter["source_path"].as_str(), chapter["content"].as_str())
+        {
+            let dir = src.join(path).parent().unwrap().to_path_buf();
+            let content = process(&dir, links, content)
+                .with_context(|| format!("failed to process chapter `{}`", path))?;
+            chapter["content"] = Value::String(content);
+        }
+        if let Some(items) = chapter["sub_items"].as_array_mut() {
+            process_items(src, links, items)?;
+        }
+    }
+    Ok(())
+}
+
+/// Processes the content of a single chapter.
+///
+/// `{{#onedoc path/to/lib.rs}}` directives outside of code are replaced with
+/// the crate docs from the Rust file, relative to the chapter. Code blocks in
+/// the crate docs are fixed, the rest of the chapter is left as written. Then
+/// intra-doc links are fixed.
+pub fn process(dir: &Path, links: &BTreeMap<String, String>, content: &str) -> Result<String> {
+    let code: Vec<_> = Parser::new_ext(content, Options::all())
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::Start(Tag::CodeBlock(_)) | Event::Code(_)))
+        .map(|(_, range)| range)
+        .collect();
+
+    let mut docs = Vec::new();
+    for caps in regex!(r"\{\{\s*#onedoc\s+(\S+?)\s*\}\}").captures_iter(content) {
+        let start = caps.get(0).unwrap().start();
+        if code.iter().any(|range| range.contains(&start)) {
+            continue;
+        }
+        let path = dir.join(&caps[1]);
+        let text =
+            get_module_comment(&path).with_context(|| format!("failed to read from `{}`", path))?;
+        docs.push((caps.get(0).unwrap().range(), text));
+    }
+
+    let mut events = Vec::new();
+    let mut last = 0;
+    for (range, text) in &docs {
+        events.extend(Parser::new_ext(&content[last..range.start], Options::all()));
+        let doc = fix::headings(0, Parser::new_ext(text, Options::all()).collect());
+        events.extend(fix::code_blocks(false, doc).context("failed to fix codeblocks")?);
+        last = range.end;
+    }
+    events.extend(Parser::new_ext(&content[last..], Options::all()));
+
+    let mut link_config = Links::new();
+    events = fix::doc_links(links, &mut link_config, events);
+    let mut rendered = render::to_cmark(events)?;
+    crate::append_links(&mut rendered, link_config, false);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapter() {
+        let links = BTreeMap::from([(
+            "String".to_owned(),
+            "https://doc.rust-lang.org/std/string/struct.String.html".to_owned(),
+        )]);
+        let content = "# Chapter\n\nSee [`String`].\n\n```\nlet x = 1;\n# let y = 2;\n```\n";
+        assert_eq!(
+            process(Path::new("src"), &links, content).unwrap(),
+            "# Chapter\n\nSee [`String`][string].\n\n```\nlet x = 1;\n# let y = 2;\n```\n\n[string]: https://doc.rust-lang.org/std/string/struct.String.html\n"
+        );
+    }
+
+    #[test]
+    fn directives() {
+        let dir = std::env::temp_dir().join(format!("onedoc-mdbook-{}", std::process::id()));
+        let dir = camino::Utf8PathBuf::try_from(dir).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("lib.rs"),
+            "//! # Crate\n//!\n//! ```\n//! let x = 1;\n//! ```\n",
+        )
+        .unwrap();
+
+        let content =
+            "{{#onedoc lib.rs}}\n\n```\n{{#onedoc lib.rs}}\n```\n\nUse `{{#onedoc lib.rs}}`.\n";
+        let result = process(&dir, &BTreeMap::new(), content);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            result.unwrap(),
+            "## Crate\n\n```rust\nlet x = 1;\n```\n\n```\n{{#onedoc lib.rs}}\n```\n\nUse `{{#onedoc lib.rs}}`."
+        );
+    }
+}
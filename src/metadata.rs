@@ -0,0 +1,245 @@
+//! Template values built from the Cargo metadata.
+
+use camino::{Utf8Component, Utf8Path as Path};
+use cargo_metadata::{DependencyKind, Metadata, Package};
+use serde::Serialize;
+
+use crate::features;
+
+/// A dependency of the package.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    name: String,
+    /// The version requirement, e.g. `^1.0`.
+    req: String,
+    /// The resolved version, if the dependency was resolved.
+    version: Option<String>,
+    /// One of `normal`, `dev` or `build`.
+    kind: &'static str,
+    optional: bool,
+    features: Vec<String>,
+}
+
+/// A feature of the package.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Feature {
+    name: String,
+    /// Whether the feature is enabled by the `default` feature.
+    default: bool,
+    /// The `##` documentation above the feature in the manifest.
+    doc: String,
+    /// The features and dependencies enabled by this feature.
+    enables: Vec<String>,
+}
+
+/// The library and binary targets of the package.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Targets {
+    lib: Option<String>,
+    bins: Vec<String>,
+}
+
+/// The workspace the package is in.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Workspace {
+    /// The workspace root relative to the package directory, e.g. `..`.
+    root: String,
+    /// The other packages in the workspace.
+    packages: Vec<Sibling>,
+}
+
+/// Another package in the workspace.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Sibling {
+    name: String,
+    version: String,
+    description: Option<String>,
+    /// The package directory relative to the workspace root.
+    path: String,
+    /// The package directory relative to the directory of the output file.
+    link: String,
+}
+
+/// Returns the dependencies of the package along with their resolved versions.
+pub fn dependencies(metadata: &Metadata, pkg: &Package) -> Vec<Dependency> {
+    let resolved: Vec<&Package> = metadata
+        .resolve
+        .as_ref()
+        .and_then(|r| r.nodes.iter().find(|n| n.id == pkg.id))
+        .map(|node| node.dependencies.iter().map(|id| &metadata[id]).collect())
+        .unwrap_or_default();
+
+    pkg.dependencies
+        .iter()
+        .map(|dep| Dependency {
+            name: dep.name.clone(),
+            req: dep.req.to_string(),
+            version: resolved
+                .iter()
+                .find(|p| p.name == dep.name && dep.req.matches(&p.version))
+                .map(|p| p.version.to_string()),
+            kind: match dep.kind {
+                DependencyKind::Development => "dev",
+                DependencyKind::Build => "build",
+                _ => "normal",
+            },
+            optional: dep.optional,
+            features: dep.features.clone(),
+        })
+        .collect()
+}
+
+/// Returns the features of the package, excluding the `default` feature.
+///
+/// Features are in manifest order, followed by any implicit features for
+/// optional dependencies.
+pub fn features(pkg: &Package, items: &[features::Item]) -> Vec<Feature> {
+    let default = pkg.features.get("default");
+    let docs: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            features::Item::Feature { name, doc } => Some((name, doc)),
+            features::Item::Text(_) => None,
+        })
+        .collect();
+    let rest = pkg
+        .features
+        .keys()
+        .filter(|name| *name != "default" && !docs.iter().any(|(n, _)| n == name));
+
+    docs.iter()
+        .map(|(name, doc)| (*name, doc.as_str()))
+        .chain(rest.map(|name| (name, "")))
+        .filter_map(|(name, doc)| {
+            Some(Feature {
+                name: name.clone(),
+                default: default.is_some_and(|d| d.contains(name)),
+                doc: doc.to_owned(),
+                enables: pkg.features.get(name)?.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the library and binary targets of the package.
+pub fn targets(pkg: &Package) -> Targets {
+    let is = |t: &cargo_metadata::Target, kind: &str| t.kind.iter().any(|k| k == kind);
+    Targets {
+        lib: pkg
+            .targets
+            .iter()
+            .find(|t| is(t, "lib") || is(t, "proc-macro"))
+            .map(|t| t.name.clone()),
+        bins: pkg
+            .targets
+            .iter()
+            .filter(|t| is(t, "bin"))
+            .map(|t| t.name.clone())
+            .collect(),
+    }
+}
+
+/// Returns the workspace root and the other packages in the workspace.
+///
+/// `output_dir` is the directory of the output file, links to the other
+/// packages are relative to it.
+pub fn workspace(metadata: &Metadata, pkg: &Package, output_dir: &Path) -> Workspace {
+    let root = &metadata.workspace_root;
+    let depth = pkg
+        .manifest_path
+        .parent()
+        .unwrap()
+        .strip_prefix(root)
+        .map_or(0, |p| p.components().count());
+    Workspace {
+        root: match depth {
+            0 => ".".to_owned(),
+            n => vec![".."; n].join("/"),
+        },
+        packages: metadata
+            .workspace_packages()
+            .into_iter()
+            .filter(|p| p.id != pkg.id)
+            .map(|p| {
+                let dir = p.manifest_path.parent().unwrap();
+                Sibling {
+                    name: p.name.clone(),
+                    version: p.version.to_string(),
+                    description: p.description.clone(),
+                    path: dir.strip_prefix(root).unwrap_or(dir).to_string(),
+                    link: relative(output_dir, dir),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Returns the path relative to the directory, both paths must be absolute.
+fn relative(dir: &Path, path: &Path) -> String {
+    // Lexically remove `.` and `..` components first
+    let components = |p: &'_ Path| {
+        let mut components = Vec::new();
+        for c in p.components() {
+            match c {
+                Utf8Component::CurDir => {}
+                Utf8Component::ParentDir => {
+                    components.pop();
+                }
+                c => components.push(c.as_str().to_owned()),
+            }
+        }
+        components
+    };
+    let (dir, path) = (components(dir), components(path));
+    let common = dir.iter().zip(&path).take_while(|(a, b)| a == b).count();
+    let relative: Vec<_> = std::iter::repeat_n("..", dir.len() - common)
+        .chain(path[common..].iter().map(String::as_str))
+        .collect();
+    if relative.is_empty() {
+        ".".to_owned()
+    } else {
+        relative.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> Metadata {
+        cargo_metadata::MetadataCommand::new().exec().unwrap()
+    }
+
+    #[test]
+    fn this_package() {
+        let metadata = metadata();
+        let pkg = metadata.root_package().unwrap();
+
+        let deps = dependencies(&metadata, pkg);
+        let anyhow = deps.iter().find(|d| d.name == "anyhow").unwrap();
+        assert_eq!(anyhow.kind, "normal");
+        assert!(anyhow.version.is_some());
+
+        assert_eq!(
+            targets(pkg),
+            Targets {
+                lib: None,
+                bins: vec!["cargo-onedoc".into()],
+            }
+        );
+        let workspace = workspace(&metadata, pkg, &metadata.workspace_root);
+        assert_eq!(workspace.root, ".");
+        assert!(workspace.packages.is_empty());
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(relative("/ws".into(), "/ws/crates/a".into()), "crates/a");
+        assert_eq!(
+            relative("/ws/docs".into(), "/ws/crates/a".into()),
+            "../crates/a"
+        );
+        assert_eq!(relative("/ws/crates/a".into(), "/ws/crates/a".into()), ".");
+        assert_eq!(relative("/ws/a/../docs".into(), "/ws/b".into()), "../b");
+    }
+}
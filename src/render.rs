@@ -1,8 +1,11 @@
 use std::borrow::Borrow;
 
 use anyhow::Result;
-use pulldown_cmark::Event;
+use pulldown_cmark::{Event, HeadingLevel};
 use pulldown_cmark_to_cmark::{cmark_resume_with_options, Options};
+use pulldown_cmark_toc as toc;
+
+use crate::target::{Anchors, Target};
 
 /// Render Markdown events as Markdown.
 pub fn to_cmark<'a, I, E>(events: I) -> Result<String>
@@ -19,3 +22,21 @@ where
     cmark_resume_with_options(events.into_iter(), &mut buf, None, opts)?.finalize(&mut buf)?;
     Ok(buf)
 }
+
+/// Render a table of contents for the Markdown using the target's anchors.
+pub fn toc(text: &str, target: Target) -> String {
+    let levels = HeadingLevel::H2..=HeadingLevel::H6;
+    let toc = toc::TableOfContents::new(text);
+    let rendered = toc.to_cmark_with_options(toc::Options::default().levels(levels.clone()));
+    let headings = toc.headings().filter(|h| levels.contains(&h.level()));
+
+    let mut anchors = Anchors::new(target);
+
+    let mut buf = String::new();
+    for (line, heading) in rendered.lines().zip(headings) {
+        let anchor = anchors.next(&heading.text());
+        let title = &line[..line.rfind("](#").unwrap()];
+        buf.push_str(&format!("{title}](#{anchor})\n"));
+    }
+    buf
+}
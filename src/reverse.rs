@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::{bail, Context as _, Result};
+use pulldown_cmark::{Options, Parser};
+
+use crate::config::{Config, Doc};
+use crate::{fix, render};
+
+/// Generate the `//!` crate docs for the output Rust file from Markdown inputs.
+///
+/// Returns the new contents of the output file, the existing `//!` block is
+/// replaced in place and the rest of the file, including any leading license
+/// header or attributes, is preserved. If there is no `//!` block then the docs
+/// are added at the top.
+pub fn generate(config: &Config, doc: &Doc, current: &str) -> Result<String> {
+    if doc.output.extension() != Some("rs") {
+        bail!("output `{}` must be a Rust file", doc.output);
+    }
+
+    // Map the URLs in the links config back to their names, picking the first
+    // name so that the output is deterministic.
+    let config = config.links(doc);
+    let mut links = BTreeMap::new();
+    for (name, url) in &config {
+        links.entry(url.as_str()).or_insert(name.as_str());
+    }
+
+    let mut texts = Vec::new();
+    for input in doc.files.iter().map(|f| &f.path) {
+        if input.extension() != Some("md") {
+            bail!("unsupported file extension `{}`", input);
+        }
+        let text = fs::read_to_string(input)
+            .with_context(|| format!("failed to read from `{}`", input))?;
+        let text = fix::markers(&text, &doc.output).context("failed to process markers")?;
+        texts.push(text);
+    }
+
+    let mut events = Vec::new();
+    for text in &texts {
+        let mut es = Vec::from_iter(Parser::new_ext(text, Options::all()));
+        es = fix::reverse::headings(es);
+        es = fix::reverse::code_blocks(es);
+        es = fix::reverse::doc_links(&links, es);
+        events.extend(es);
+    }
+
+    let mut docs = render::to_cmark(&events).context("failed to render contents")?;
+
+    // Collapse links where the text is the same as the name
+    for name in links.values() {
+        docs = docs.replace(&format!("[`{name}`]({name})"), &format!("[`{name}`]"));
+        docs = docs.replace(&format!("[{name}]({name})"), &format!("[{name}]"));
+    }
+
+    let mut block = String::new();
+    for line in docs.lines() {
+        match line {
+            "" => block.push_str("//!\n"),
+            line => {
+                block.push_str("//! ");
+                block.push_str(line);
+                block.push('\n');
+            }
+        }
+    }
+    Ok(replace_docs(current, &block))
+}
+
+/// Replaces the first `//!` block in the Rust source with the given lines.
+///
+/// The lines before and after the block are preserved. If there is no `//!`
+/// block then the lines are added at the top.
+pub fn replace_docs(current: &str, block: &str) -> String {
+    let lines: Vec<_> = current.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| line.starts_with("//!"))
+        .unwrap_or(0);
+    let end = start
+        + lines[start..]
+            .iter()
+            .take_while(|line| line.starts_with("//!"))
+            .count();
+
+    let mut result = String::new();
+    for line in &lines[..start] {
+        result.push_str(line);
+        result.push('\n');
+    }
+    result.push_str(block);
+    for line in &lines[end..] {
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use camino::Utf8PathBuf as PathBuf;
+
+    use crate::config::File;
+
+    fn generate_from(name: &str, markdown: &str, current: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("onedoc-{}-{}", name, std::process::id()));
+        let dir = PathBuf::try_from(dir).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("crate.md"), markdown).unwrap();
+
+        let mut config: Config = toml::from_str(
+            r#"
+[[doc]]
+input = "crate.md"
+output = "src/lib.rs"
+direction = "to-rustdoc"
+"#,
+        )
+        .unwrap();
+        let doc = &mut config.docs[0];
+        doc.files = vec![File::from(dir.join("crate.md"))];
+        let result = generate(&config, &config.docs[0], current);
+        fs::remove_dir_all(&dir).unwrap();
+        result.unwrap()
+    }
+
+    #[test]
+    fn replace_docs_after_header() {
+        let current = "// Copyright X\n#![deny(missing_docs)]\n//! Old docs.\n//!\n//! More.\n\npub fn f() {}\n";
+        assert_eq!(
+            generate_from("header", "# Crate\n\nNew docs.\n", current),
+            "// Copyright X\n#![deny(missing_docs)]\n//! # Crate\n//!\n//! New docs.\n\npub fn f() {}\n"
+        );
+    }
+
+    #[test]
+    fn add_docs_without_existing_block() {
+        assert_eq!(
+            generate_from("no-block", "New docs.\n", "pub fn f() {}\n"),
+            "//! New docs.\npub fn f() {}\n"
+        );
+    }
+}
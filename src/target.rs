@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use regex_macro::regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Alerts;
+
+/// The destination that the output will be rendered on.
+///
+/// Each host supports a slightly different subset of Markdown, the target
+/// selects a rendering profile that adjusts the output accordingly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Target {
+    /// GitHub flavored Markdown.
+    #[default]
+    Github,
+    /// GitLab flavored Markdown.
+    Gitlab,
+    /// The crates.io README renderer.
+    CratesIo,
+    /// Rustdoc, e.g. when including the output using `include_str!`.
+    DocsRs,
+}
+
+impl Target {
+    /// The default way to render alerts.
+    pub fn alerts(self) -> Alerts {
+        match self {
+            Self::Github => Alerts::Github,
+            Self::Gitlab | Self::CratesIo | Self::DocsRs => Alerts::Blockquote,
+        }
+    }
+
+    /// Whether intra-doc links should be rewritten using the links config.
+    ///
+    /// Rustdoc resolves intra-doc links itself so they are left as is.
+    pub fn rewrite_doc_links(self) -> bool {
+        !matches!(self, Self::DocsRs)
+    }
+
+    /// Whether hidden `#` lines should be removed from Rust code blocks.
+    ///
+    /// Rustdoc hides these lines itself and they are required for doctests.
+    pub fn strip_hidden_lines(self) -> bool {
+        !matches!(self, Self::DocsRs)
+    }
+
+    /// Whether raw HTML comments should be removed.
+    pub fn strip_html_comments(self) -> bool {
+        matches!(self, Self::CratesIo)
+    }
+
+    /// Generate an anchor for the given heading text.
+    pub fn anchor(self, text: &str) -> String {
+        let anchor = regex!(r"[^\w\- ]")
+            .replace_all(&text.to_lowercase().replace(' ', "-"), "")
+            .into_owned();
+        match self {
+            Self::Gitlab => regex!(r"-{2,}").replace_all(&anchor, "-").into_owned(),
+            Self::Github | Self::CratesIo | Self::DocsRs => anchor,
+        }
+    }
+}
+
+/// Generates unique anchors for headings in document order.
+///
+/// Duplicate anchors are suffixed with `-1`, `-2` and so on, the same way as
+/// GitHub does.
+#[derive(Debug)]
+pub struct Anchors {
+    target: Target,
+    counts: HashMap<String, usize>,
+}
+
+impl Anchors {
+    pub fn new(target: Target) -> Self {
+        Self {
+            target,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Returns the unique anchor for the next heading with the given text.
+    pub fn next(&mut self, text: &str) -> String {
+        let anchor = self.target.anchor(text);
+        let i = self
+            .counts
+            .entry(anchor.clone())
+            .and_modify(|i| *i += 1)
+            .or_insert(0);
+        match *i {
+            0 => anchor,
+            i => format!("{}-{}", anchor, i),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchor() {
+        assert_eq!(Target::Github.anchor("Foo - Bar!"), "foo---bar");
+        assert_eq!(Target::Gitlab.anchor("Foo - Bar!"), "foo-bar");
+        assert_eq!(Target::DocsRs.anchor("`Foo` bar"), "foo-bar");
+    }
+
+    #[test]
+    fn unique_anchors() {
+        let mut anchors = Anchors::new(Target::Github);
+        assert_eq!(anchors.next("Usage"), "usage");
+        assert_eq!(anchors.next("Usage"), "usage-1");
+        assert_eq!(anchors.next("usage"), "usage-2");
+    }
+}
@@ -0,0 +1,628 @@
+//! Template partials and inheritance.
+//!
+//! Every file in the templates directory is registered with the engine using
+//! its path relative to the directory, so that templates can include it using
+//! `{% include "footer.md" %}`.
+//!
+//! A template can also extend a base template using `{% extends "base.md" %}`
+//! on its first line. The base template marks overridable regions using
+//! `{% block name %}...{% endblock %}` and the extending template overrides
+//! them using the same tags, anything outside of the blocks is ignored.
+//! Blocks can't be nested.
+//!
+//! A few built-in templates are embedded in the binary and selected using
+//! `template = "builtin:<name>"`.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+use regex_macro::regex;
+
+/// The built-in templates, the first is used when a doc doesn't specify one.
+pub const BUILTINS: &[(&str, &str)] = &[
+    ("library", include_str!("templates/library.md")),
+    ("minimal", include_str!("templates/minimal.md")),
+    ("binary", include_str!("templates/binary.md")),
+    (
+        "workspace-index",
+        include_str!("templates/workspace-index.md"),
+    ),
+];
+
+/// Returns the built-in template name if the path is of the form
+/// `builtin:<name>`.
+pub fn builtin_name(path: &Path) -> Option<&str> {
+    path.as_str().strip_prefix("builtin:")
+}
+
+/// Returns the source of the built-in template with the given name.
+pub fn builtin(name: &str) -> Result<&'static str> {
+    match BUILTINS.iter().find(|(n, _)| *n == name) {
+        Some((_, source)) => Ok(source),
+        None => {
+            let names: Vec<_> = BUILTINS.iter().map(|(n, _)| format!("`{}`", n)).collect();
+            bail!(
+                "unknown built-in template `{}`, expected one of {}",
+                name,
+                names.join(", ")
+            )
+        }
+    }
+}
+
+/// The template engine along with where each compiled template came from, so
+/// that errors point at the original templates.
+pub struct Engine<'a> {
+    engine: upon::Engine<'a>,
+    compiled: BTreeMap<String, Compiled>,
+}
+
+impl Engine<'_> {
+    /// Returns an engine with the standard filters registered.
+    pub fn new() -> Self {
+        let mut engine = upon::Engine::new();
+        crate::filters::register(&mut engine);
+        Self {
+            engine,
+            compiled: BTreeMap::new(),
+        }
+    }
+
+    /// Returns whether a template with the name is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.engine.get_template(name).is_some()
+    }
+
+    /// Compiles and registers a template, see [`compile`].
+    pub fn add(
+        &mut self,
+        name: &str,
+        source: &str,
+        lookup: &dyn Fn(&str) -> Result<String>,
+    ) -> Result<()> {
+        let compiled = compile(name, source, lookup, &|n| self.contains(n))?;
+        self.insert(name, compiled)
+    }
+
+    /// Registers every file in the templates directory.
+    pub fn register_dir(&mut self, dir: &Path) -> Result<()> {
+        let pattern = dir.join("**/*");
+        let mut files = BTreeMap::new();
+        for path in glob::glob(pattern.as_str())? {
+            let path = PathBuf::try_from(path?)?;
+            if path.is_file() {
+                let name = path.strip_prefix(dir).unwrap().as_str().replace('\\', "/");
+                files.insert(name, path);
+            }
+        }
+
+        for (name, path) in &files {
+            let source = fs::read_to_string(path)
+                .with_context(|| format!("failed to read from `{}`", path))?;
+            let compiled = compile(name, &source, &dir_lookup(dir), &|n| files.contains_key(n))?;
+            self.insert(name, compiled)?;
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, name: &str, compiled: Compiled) -> Result<()> {
+        self.engine
+            .add_template(name.to_owned(), compiled.source.clone())
+            .map_err(|e| error(e, Some(&compiled), None))?;
+        self.compiled.insert(name.to_owned(), compiled);
+        Ok(())
+    }
+
+    /// Renders the template with the given values.
+    pub fn render(&self, name: &str, values: &upon::Value) -> Result<String> {
+        let template = self
+            .engine
+            .get_template(name)
+            .with_context(|| format!("template `{}` is not registered", name))?;
+        template.render_from(values).to_string().map_err(|e| {
+            // Render errors don't know which template they came from, it is
+            // either this one or one that it includes
+            let compiled = self
+                .compiled
+                .get(name)
+                .into_iter()
+                .chain(self.compiled.values())
+                .find(|c| c.contains(&e));
+            error(e, compiled, Some(values))
+        })
+    }
+}
+
+/// Returns a lookup that reads base templates from the directory.
+pub fn dir_lookup(dir: &Path) -> impl Fn(&str) -> Result<String> + '_ {
+    move |name| {
+        let path = dir.join(name);
+        fs::read_to_string(&path).with_context(|| format!("failed to read from `{}`", path))
+    }
+}
+
+/// A lookup for base templates that only knows the built-in templates.
+pub fn builtin_lookup(name: &str) -> Result<String> {
+    builtin(name).map(str::to_owned)
+}
+
+/// A template with `extends` and `block` tags resolved, along with where each
+/// part of it came from.
+#[derive(Debug)]
+pub struct Compiled {
+    /// The compiled source.
+    pub source: String,
+    /// The source of each template that contributed to it.
+    files: BTreeMap<String, String>,
+    /// The start of each segment of the compiled source, along with the
+    /// template and the offset in it that the segment came from.
+    segments: Vec<(usize, String, usize)>,
+}
+
+impl Compiled {
+    /// Returns whether the error's line matches this template, i.e. whether
+    /// the error probably came from it.
+    fn contains(&self, err: &upon::Error) -> bool {
+        let msg = format!("{:#}", err);
+        Location::parse(&msg)
+            .is_some_and(|loc| self.source.lines().nth(loc.line - 1) == Some(loc.text))
+    }
+
+    /// Returns the template name, line, column and line of text that a line
+    /// and column in the compiled source came from.
+    fn locate(&self, line: usize, col: usize) -> Option<(&str, usize, usize, &str)> {
+        let start: usize = self
+            .source
+            .split_inclusive('\n')
+            .take(line - 1)
+            .map(str::len)
+            .sum();
+        let offset = start
+            + self.source[start..]
+                .char_indices()
+                .nth(col - 1)
+                .map_or(0, |(i, _)| i);
+        let i = self.segments.partition_point(|(s, ..)| *s <= offset);
+        let (segment, name, original) = self.segments.get(i.checked_sub(1)?)?;
+        let source = self.files.get(name)?;
+        let offset = (original + offset - segment).min(source.len());
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        Some((
+            name,
+            source[..offset].matches('\n').count() + 1,
+            source[line_start..offset].chars().count() + 1,
+            &source[line_start..line_end],
+        ))
+    }
+}
+
+/// The location of an error, as shown by upon.
+struct Location<'a> {
+    /// The template name, or `<anonymous>` for render errors.
+    name: &'a str,
+    /// One-indexed line number.
+    line: usize,
+    /// One-indexed column number.
+    col: usize,
+    /// The number of highlighted characters.
+    width: usize,
+    /// The line of the template.
+    text: &'a str,
+    /// The reason along with any other notes.
+    notes: Vec<&'a str>,
+}
+
+impl<'a> Location<'a> {
+    /// Parses the location from the alternate formatting of an error, upon
+    /// doesn't expose it any other way.
+    fn parse(msg: &'a str) -> Option<Self> {
+        let lines: Vec<_> = msg.lines().collect();
+        let caps = regex!(r"^\s*--> (.+):(\d+):(\d+)$").captures(lines.get(2)?)?;
+        let (_, text) = lines.get(4)?.split_once(" | ")?;
+        Some(Self {
+            name: caps.get(1)?.as_str(),
+            line: caps[2].parse().ok().filter(|&n| n > 0)?,
+            col: caps[3].parse().ok().filter(|&n| n > 0)?,
+            width: lines.get(5)?.matches('^').count(),
+            text,
+            notes: lines.get(7..)?.iter().map(|l| l.trim()).collect(),
+        })
+    }
+}
+
+/// Converts a template error into one that shows the template name, line,
+/// column and a snippet of the original template.
+///
+/// When rendering fails because of an unknown variable the available
+/// variables are listed.
+fn error(
+    err: upon::Error,
+    compiled: Option<&Compiled>,
+    values: Option<&upon::Value>,
+) -> anyhow::Error {
+    let full = format!("{:#}", err);
+    let (mut msg, pad) = match Location::parse(&full) {
+        Some(loc) => {
+            let (name, line, col, text) = compiled
+                .and_then(|c| c.locate(loc.line, loc.col))
+                .unwrap_or((loc.name, loc.line, loc.col, loc.text));
+            let num = line.to_string();
+            let pad = " ".repeat(num.len());
+            let underline = format!(
+                "{}{}{}",
+                " ".repeat(col - 1),
+                "^".repeat(loc.width),
+                "-".repeat(3_usize.saturating_sub(loc.width))
+            );
+            let mut msg = format!(
+                "{}\n\n {pad}--> {name}:{num}:{col}\n {pad} |\n {num} | {text}\n {pad} | {underline}\n {pad} |",
+                full.lines().next().unwrap_or_default(),
+            );
+            for note in loc.notes {
+                msg.push_str(&format!("\n {pad} {note}"));
+            }
+            (msg, pad)
+        }
+        None => (full.trim_end().to_owned(), " ".to_owned()),
+    };
+    if msg.contains("reason: unknown filter") {
+        msg.push_str(&format!(
+            "\n {pad} = help: run `cargo onedoc filters` to list the available filters"
+        ));
+    }
+    if let Some(upon::Value::Map(map)) = values {
+        if msg.contains("reason: not found in this scope") {
+            let names: Vec<_> = map.keys().map(|k| format!("`{}`", k)).collect();
+            msg.push_str(&format!(
+                "\n {pad} = help: available variables are {}",
+                names.join(", ")
+            ));
+        }
+    }
+    anyhow!("{}", msg)
+}
+
+/// Returns the template with `extends` and `block` tags resolved.
+///
+/// Base templates are loaded using `lookup`, see [`dir_lookup`]. Each include
+/// is checked using `exists` so that a missing partial names the including
+/// template and line.
+pub fn compile(
+    name: &str,
+    source: &str,
+    lookup: &dyn Fn(&str) -> Result<String>,
+    exists: &dyn Fn(&str) -> bool,
+) -> Result<Compiled> {
+    let mut compiled = Compiled {
+        source: String::new(),
+        files: BTreeMap::new(),
+        segments: Vec::new(),
+    };
+    let parts = resolve(name, source, lookup, exists, &mut compiled.files)?;
+
+    // Every block is surrounded by tags, remove them along with their line if
+    // they are on their own
+    let mut after_block = false;
+    for part in &parts {
+        let (span, tag) = match part {
+            Part::Text(span) => (span, after_block),
+            Part::Block(_, body) => (body, true),
+        };
+        let skip = if tag {
+            compiled.remove_tag(&span.text)
+        } else {
+            0
+        };
+        compiled.push(span, skip);
+        after_block = matches!(part, Part::Block(..));
+    }
+    Ok(compiled)
+}
+
+impl Compiled {
+    /// Appends the span, skipping the given number of bytes.
+    fn push(&mut self, span: &Span, skip: usize) {
+        let text = &span.text[skip..];
+        if !text.is_empty() {
+            self.segments
+                .push((self.source.len(), span.name.clone(), span.offset + skip));
+            self.source.push_str(text);
+        }
+    }
+
+    /// Removes the line of a tag between the source so far and the next text
+    /// if the tag is on its own, returning how much of the next text to skip.
+    fn remove_tag(&mut self, next: &str) -> usize {
+        let end = self.source.trim_end_matches([' ', '\t']).len();
+        let rest = next.trim_start_matches([' ', '\t']);
+        if (end == 0 || self.source[..end].ends_with('\n')) && rest.starts_with('\n') {
+            self.source.truncate(end);
+            self.segments.retain(|(start, ..)| *start < end);
+            next.len() - rest.len() + 1
+        } else {
+            0
+        }
+    }
+}
+
+/// A piece of a template.
+#[derive(Debug, Clone)]
+struct Span {
+    /// The name of the template.
+    name: String,
+    /// The offset in the template.
+    offset: usize,
+    text: String,
+}
+
+/// A template split at its block tags.
+#[derive(Debug)]
+enum Part {
+    Text(Span),
+    /// A block and its body, the tags themselves aren't included.
+    Block(String, Span),
+}
+
+/// Resolves the `extends` tag, returning the parts of the base template with
+/// its blocks overridden.
+///
+/// The source of every template involved is added to `files`.
+fn resolve(
+    name: &str,
+    source: &str,
+    lookup: &dyn Fn(&str) -> Result<String>,
+    exists: &dyn Fn(&str) -> bool,
+    files: &mut BTreeMap<String, String>,
+) -> Result<Vec<Part>> {
+    if files.contains_key(name) {
+        bail!("template `{}` extends itself", name);
+    }
+    files.insert(name.to_owned(), source.to_owned());
+    check_includes(name, source, exists)?;
+    let parts = parse(name, source)?;
+
+    let Some(caps) = regex!(r#"^\s*\{%\s*extends\s+"([^"]+)"\s*%\}"#).captures(source) else {
+        return Ok(parts);
+    };
+    let base_name = &caps[1];
+    let base = lookup(base_name).with_context(|| {
+        format!(
+            "failed to load base template `{}` extended by `{}`",
+            base_name, name
+        )
+    })?;
+    let base = resolve(base_name, &base, lookup, exists, files)?;
+
+    let mut overrides: BTreeMap<_, _> = parts
+        .into_iter()
+        .filter_map(|part| match part {
+            Part::Block(block, body) => Some((block, body)),
+            Part::Text(_) => None,
+        })
+        .collect();
+    Ok(base
+        .into_iter()
+        .map(|part| match part {
+            Part::Block(block, body) => {
+                let body = overrides.remove(&block).unwrap_or(body);
+                Part::Block(block, body)
+            }
+            part => part,
+        })
+        .collect())
+}
+
+/// Splits the template at its block tags, checking that every block is
+/// closed and that blocks aren't nested.
+///
+/// The result always starts and ends with text and alternates between text
+/// and blocks.
+fn parse(name: &str, source: &str) -> Result<Vec<Part>> {
+    let span = |start: usize, end: usize| Span {
+        name: name.to_owned(),
+        offset: start,
+        text: source[start..end].to_owned(),
+    };
+    let mut parts = Vec::new();
+    let mut last = 0;
+    let mut open: Option<&str> = None;
+    for caps in regex!(r"\{%\s*(?:block\s+(\w+)|endblock)\s*%\}").captures_iter(source) {
+        let m = caps.get(0).unwrap();
+        let line = source[..m.start()].matches('\n').count() + 1;
+        match (caps.get(1), open) {
+            (Some(block), Some(parent)) => bail!(
+                "block `{}` is nested in block `{}` at `{}:{}`, nested blocks are not supported",
+                block.as_str(),
+                parent,
+                name,
+                line
+            ),
+            (Some(block), None) => {
+                parts.push(Part::Text(span(last, m.start())));
+                open = Some(block.as_str());
+            }
+            (None, Some(block)) => {
+                parts.push(Part::Block(block.to_owned(), span(last, m.start())));
+                open = None;
+            }
+            (None, None) => bail!(
+                "`endblock` without a matching `block` at `{}:{}`",
+                name,
+                line
+            ),
+        }
+        last = m.end();
+    }
+    if let Some(block) = open {
+        bail!("block `{}` in `{}` is missing an `endblock`", block, name);
+    }
+    parts.push(Part::Text(span(last, source.len())));
+    Ok(parts)
+}
+
+/// Checks that every included template exists.
+fn check_includes(name: &str, source: &str, exists: &dyn Fn(&str) -> bool) -> Result<()> {
+    for caps in regex!(r#"\{%\s*include\s+"([^"]+)""#).captures_iter(source) {
+        let m = caps.get(0).unwrap();
+        if !exists(&caps[1]) {
+            let line = source[..m.start()].matches('\n').count() + 1;
+            bail!(
+                "template `{}` included from `{}:{}` not found",
+                &caps[1],
+                name,
+                line
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtins_compile() {
+        let mut engine = upon::Engine::new();
+        crate::filters::register(&mut engine);
+        for (name, source) in BUILTINS {
+            assert!(engine.compile(source).is_ok(), "{}", name);
+        }
+        assert_eq!(builtin_name(Path::new("builtin:minimal")), Some("minimal"));
+        assert!(builtin("unknown").is_err());
+    }
+
+    #[test]
+    fn unknown_variable() {
+        let mut engine = Engine::new();
+        engine
+            .add(
+                "README_TEMPLATE.md",
+                "# Title\n\n{{ summry }}\n",
+                &builtin_lookup,
+            )
+            .unwrap();
+        let values = upon::value! { summary: "Summary", contents: "" };
+        let err = engine.render("README_TEMPLATE.md", &values).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "\
+render error
+
+  --> README_TEMPLATE.md:3:4
+   |
+ 3 | {{ summry }}
+   |    ^^^^^^
+   |
+   = reason: not found in this scope
+   = help: available variables are `contents`, `summary`"
+        );
+    }
+
+    #[test]
+    fn errors_point_at_original_templates() {
+        let lookup = |_: &str| {
+            Ok("# Title\n\n{% block body %}\nBody\n{% endblock %}\n\n{{ footer }}\n".to_owned())
+        };
+        let mut engine = Engine::new();
+        engine
+            .add(
+                "child.md",
+                "{% extends \"base.md\" %}\n\n{% block body %}\nSee {{ usage }}\n{% endblock %}\n",
+                &lookup,
+            )
+            .unwrap();
+        let values = upon::value! { footer: "" };
+        let err = engine.render("child.md", &values).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("  --> child.md:4:8\n   |\n 4 | See {{ usage }}\n   |        ^^^^^\n"),
+            "{}",
+            err
+        );
+
+        let values = upon::value! { usage: "" };
+        let err = engine.render("child.md", &values).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("  --> base.md:7:4\n   |\n 7 | {{ footer }}\n   |    ^^^^^^\n"),
+            "{}",
+            err
+        );
+
+        engine
+            .add("filter.md", "# Title\n\n{{ x | nope }}\n", &builtin_lookup)
+            .unwrap();
+        let err = engine
+            .render("filter.md", &upon::value! { x: "" })
+            .unwrap_err();
+        assert!(
+            err.to_string().ends_with(
+                "  --> filter.md:3:8\n   |\n 3 | {{ x | nope }}\n   |        ^^^^\n   |\n   = reason: unknown filter or formatter\n   = help: run `cargo onedoc filters` to list the available filters"
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn missing_include() {
+        let err = compile(
+            "README.md",
+            "# Title\n\n{% include \"footer.md\" %}\n",
+            &|_| unreachable!(),
+            &|_| false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "template `footer.md` included from `README.md:3` not found"
+        );
+    }
+
+    #[test]
+    fn blocks_without_extends() {
+        let source = "# Title\n{% block body %}\nDefault\n{% endblock %}\n";
+        assert_eq!(
+            compile("base.md", source, &|_| unreachable!(), &|_| true)
+                .unwrap()
+                .source,
+            "# Title\nDefault\n"
+        );
+    }
+
+    #[test]
+    fn extends_base() {
+        let lookup = |name: &str| {
+            match name {
+            "base.md" => Ok(
+                "# {% block title %}Base{% endblock %}\n\n{% block body %}\nBody\n{% endblock %}\nFooter\n"
+                    .to_owned(),
+            ),
+            _ => bail!("not found"),
+        }
+        };
+        let source = "{% extends \"base.md\" %}\n{% block title %}Child{% endblock %}\n";
+        assert_eq!(
+            compile("child.md", source, &lookup, &|_| true)
+                .unwrap()
+                .source,
+            "# Child\n\nBody\nFooter\n"
+        );
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let source = "{% block outer %}\n{% block inner %}\n{% endblock %}\n{% endblock %}\n";
+        let err = compile("base.md", source, &|_| unreachable!(), &|_| true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "block `inner` is nested in block `outer` at `base.md:2`, nested blocks are not supported"
+        );
+    }
+}